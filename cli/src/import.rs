@@ -0,0 +1,214 @@
+//! `payroll import`: creates payrolls from a CSV file of
+//! `receiver,amount,schedule,cycles` rows.
+
+use crate::CliResult;
+use clockpay_client::{BatchPayroll, ClockPayClient};
+use program::{instruction::NewPayrollArgs, state::Payroll};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+use std::{collections::HashSet, fs};
+
+/// What happened to one row of the file.
+pub struct RowReport {
+    /// 1-based line number in the file.
+    pub line: usize,
+    pub receiver: Option<Pubkey>,
+    /// The signature of the transaction that created the payroll, `None` on
+    /// a dry run, or why the row was rejected.
+    pub outcome: Result<Option<Signature>, String>,
+}
+
+struct Row {
+    line: usize,
+    receiver: Option<Pubkey>,
+    args: Result<NewPayrollArgs, String>,
+}
+
+pub struct ImportOptions {
+    pub mint: Option<Pubkey>,
+    pub start: bool,
+    pub allow_overcommit: bool,
+    pub dry_run: bool,
+}
+
+/// Validates every row of `path`, then creates the valid ones in batches.
+/// Fails before sending anything if the vault can't cover the new payrolls,
+/// unless `allow_overcommit` is set.
+pub fn import(
+    client: &ClockPayClient,
+    keypair: &Keypair,
+    path: &str,
+    options: &ImportOptions,
+) -> CliResult<Vec<RowReport>> {
+    let contents = fs::read_to_string(path)?;
+    let mut rows = parse(&contents, options);
+
+    let accounting = client.accounting_address(&keypair.pubkey());
+    let (_, mint_vault) = client.get_mint_vault(&accounting, options.mint.as_ref())?;
+    let mut receivers: HashSet<Pubkey> = client
+        .get_payrolls(&accounting)?
+        .into_iter()
+        .map(|(_, payroll)| payroll.receiver)
+        .collect();
+    let mut committed = mint_vault.committed_amount as u128;
+    for row in &mut rows {
+        let (Some(receiver), Ok(args)) = (row.receiver, &row.args) else {
+            continue;
+        };
+        if !receivers.insert(receiver) {
+            row.args = Err("receiver already has a payroll".to_string());
+            continue;
+        }
+        let cycles = if args.cycles == Payroll::OPEN_ENDED {
+            1
+        } else {
+            args.cycles
+        };
+        committed += args.amount as u128 * cycles as u128;
+    }
+    if !options.allow_overcommit && committed > mint_vault.balance as u128 {
+        return Err(format!(
+            "vault balance {} doesn't cover the {} its payrolls would be committed to; \
+             pass --allow-overcommit to import anyway",
+            mint_vault.balance, committed
+        )
+        .into());
+    }
+
+    let mut reports = vec![];
+    let mut batch = vec![];
+    for row in rows {
+        match row.args {
+            Ok(args) if !options.dry_run => batch.push((row.line, row.receiver, args)),
+            args => reports.push(RowReport {
+                line: row.line,
+                receiver: row.receiver,
+                outcome: args.map(|_| None),
+            }),
+        }
+    }
+    if batch.is_empty() {
+        return Ok(reports);
+    }
+
+    let payrolls: Vec<BatchPayroll> = batch
+        .iter()
+        .map(|(_, receiver, args)| BatchPayroll {
+            // Rows only have valid arguments once their receiver parsed.
+            receiver: receiver.unwrap(),
+            args: args.clone(),
+            price_feed: None,
+            deduction_destinations: vec![],
+        })
+        .collect();
+    for (range, result) in client.create_payroll_batch(keypair, &payrolls, options.start)? {
+        let outcome = result.map(Some).map_err(|e| e.to_string());
+        for (line, receiver, _) in &batch[range] {
+            reports.push(RowReport {
+                line: *line,
+                receiver: *receiver,
+                outcome: outcome.clone(),
+            });
+        }
+    }
+    reports.sort_by_key(|report| report.line);
+    Ok(reports)
+}
+
+/// Parses every row of the file. A header row, blank lines and lines
+/// starting with `#` are skipped.
+fn parse(contents: &str, options: &ImportOptions) -> Vec<Row> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .filter(|(line_number, line)| !(*line_number == 1 && line.starts_with("receiver")))
+        .map(|(line, text)| {
+            let fields = split_fields(text);
+            let receiver = fields.first().and_then(|field| field.parse().ok());
+            Row {
+                line,
+                receiver,
+                args: parse_args(&fields, options),
+            }
+        })
+        .collect()
+}
+
+fn parse_args(fields: &[String], options: &ImportOptions) -> Result<NewPayrollArgs, String> {
+    let [receiver, amount, schedule, cycles] = fields else {
+        return Err(format!("expected 4 fields, found {}", fields.len()));
+    };
+    receiver
+        .parse::<Pubkey>()
+        .map_err(|e| format!("invalid receiver: {}", e))?;
+    let amount = amount
+        .parse()
+        .map_err(|e| format!("invalid amount: {}", e))?;
+    check_schedule(schedule)?;
+    let schedule = Payroll::pack_schedule(schedule)
+        .ok_or_else(|| format!("schedule must be at most {} bytes", Payroll::SCHEDULE_LEN))?;
+    let cycles = match cycles.as_str() {
+        "" | "open-ended" => Payroll::OPEN_ENDED,
+        cycles => cycles
+            .parse()
+            .map_err(|e| format!("invalid cycles: {}", e))?,
+    };
+    if cycles == 0 {
+        return Err("cycles must be at least 1".to_string());
+    }
+
+    Ok(NewPayrollArgs {
+        amount,
+        cycles,
+        schedule,
+        withholding: vec![],
+        decimals: None,
+        mint: options.mint,
+        quote: None,
+        allow_overcommit: options.allow_overcommit,
+        start_ts: None,
+        end_ts: None,
+        prorate: false,
+    })
+}
+
+/// Catches schedules Clockwork would reject when the thread is created: its
+/// cron expressions have 6 or 7 fields, starting with seconds.
+fn check_schedule(schedule: &str) -> Result<(), String> {
+    let fields = schedule.split_whitespace().count();
+    if !(6..=7).contains(&fields) {
+        return Err(format!(
+            "schedule `{}` has {} fields, expected 6 or 7",
+            schedule, fields
+        ));
+    }
+    let valid = |c: char| c.is_ascii_alphanumeric() || "*/,-?#".contains(c);
+    if !schedule.chars().all(|c| c.is_whitespace() || valid(c)) {
+        return Err(format!("schedule `{}` has invalid characters", schedule));
+    }
+    Ok(())
+}
+
+/// Splits a CSV line on commas outside double quotes, so schedules such as
+/// `"0 0 9 1,15 * * *"` can be quoted.
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+        .iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
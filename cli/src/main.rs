@@ -0,0 +1,696 @@
+mod import;
+mod output;
+
+use clap::{Parser, Subcommand};
+use clockpay_client::{ClientError, ClockPayClient};
+use import::ImportOptions;
+use output::OutputFormat;
+use program::{
+    instruction::{
+        AdjustmentArgs, DeductionArgs, NewPayrollArgs, QuoteArgs, UpdatePayrollArgs, WithdrawArgs,
+    },
+    state::{Adjustment, DeductionKind, Payroll},
+};
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::{error::Error, process::exit, str::FromStr};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+    name = "clockpay",
+    version,
+    about = "Operate a clockpay payroll from the command line"
+)]
+struct Cli {
+    /// Filepath of the keypair that signs and pays for transactions
+    /// [default: the Solana CLI config keypair]
+    #[arg(short, long, global = true)]
+    keypair: Option<String>,
+
+    /// URL for Solana's JSON RPC or moniker (or their first letter):
+    /// [mainnet-beta, testnet, devnet, localhost] [default: the Solana CLI config URL]
+    #[arg(short = 'u', long = "url", global = true)]
+    url: Option<String>,
+
+    /// Address of the deployed clockpay program
+    #[arg(long, env = "CLOCKPAY_PROGRAM_ID")]
+    program_id: Pubkey,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Display, global = true)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create an accounting and its vault for the keypair
+    InitAccounting {
+        /// Mint of the token payrolls are paid in
+        #[arg(long)]
+        mint: Pubkey,
+        /// Pay out of this token account of the keypair's through a delegate
+        /// allowance, instead of a custodial vault
+        #[arg(long, requires = "allowance")]
+        treasury: Option<Pubkey>,
+        /// Allowance to approve on the treasury
+        #[arg(long, requires = "treasury")]
+        allowance: Option<u64>,
+    },
+    /// Replace the remaining allowance of a delegated accounting
+    SetAllowance { allowance: u64 },
+    /// Add a vault for another mint to the keypair's accounting
+    AddMint {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Add lamports to the budget that pays for receivers' token accounts
+    FundRentBudget { lamports: u64 },
+    /// Add lamports to the reserve that tops up payroll threads
+    FundFeeReserve { lamports: u64 },
+    /// Take lamports out of the budget that pays for receivers' token accounts
+    WithdrawRentBudget { lamports: u64 },
+    /// Take lamports out of the reserve that tops up payroll threads
+    WithdrawFeeReserve { lamports: u64 },
+    /// Set the balance payroll threads are topped up to from the fee reserve
+    SetThreadMinBalance { lamports: u64 },
+    /// Set whether receivers need the keypair's approval to move a payroll to a new wallet
+    SetTransferApproval {
+        #[arg(action = clap::ArgAction::Set)]
+        required: bool,
+    },
+    /// Set how many cycles' worth of pay receivers may have advanced at once (0 disables advances)
+    SetAdvanceLimit { max_cycles: u64 },
+    /// Top up the threads of all active payrolls from the fee reserve
+    FundThreads {
+        /// Authority of the accounting [default: the keypair]
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+    /// Reconcile an accounting's recorded vault balance with the vault's token account
+    Sync {
+        /// Authority of the accounting [default: the keypair]
+        #[arg(long)]
+        authority: Option<Pubkey>,
+        /// Mint whose vault to sync [default: the accounting's primary mint]
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+    /// Show the keypair's accounting
+    ShowAccounting {
+        /// Authority of the accounting [default: the keypair]
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+    /// Deposit tokens into the accounting vault
+    Deposit {
+        amount: u64,
+        /// Mint whose vault to deposit into [default: the accounting's primary mint]
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Token account to debit [default: the keypair's associated token account]
+        #[arg(long, conflicts_with = "sol")]
+        source: Option<Pubkey>,
+        /// Deposit lamports from the keypair's wallet, wrapped into a native mint vault
+        #[arg(long)]
+        sol: bool,
+    },
+    /// Withdraw tokens from the accounting vault
+    Withdraw {
+        amount: u64,
+        /// Mint whose vault to withdraw from [default: the accounting's primary mint]
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Token account to credit [default: the keypair's associated token account]
+        #[arg(long)]
+        destination: Option<Pubkey>,
+        /// Withdraw even if what is left can't cover everything the vault's
+        /// payrolls are committed to
+        #[arg(long)]
+        allow_overcommit: bool,
+    },
+    /// Manage payrolls
+    Payroll {
+        #[command(subcommand)]
+        command: PayrollCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum PayrollCommand {
+    /// Create a payroll for a receiver
+    Create {
+        receiver: Pubkey,
+        /// Gross amount paid every cycle
+        #[arg(long)]
+        amount: u64,
+        /// Number of cycles to pay
+        #[arg(long, required_unless_present = "open_ended")]
+        cycles: Option<u64>,
+        /// Pay until the payroll is cancelled instead of for a number of cycles
+        #[arg(long, conflicts_with = "cycles")]
+        open_ended: bool,
+        /// Clockwork cron schedule, e.g. "0 0 9 1 * * *"
+        #[arg(long)]
+        schedule: String,
+        /// Amount withheld every cycle, as <fixed|bps>:<value>:<destination token account>.
+        /// May be repeated.
+        #[arg(long = "deduction")]
+        deductions: Vec<DeductionSpec>,
+        /// Decimals --amount is expressed in. The payroll is rejected unless
+        /// they match the payroll mint's.
+        #[arg(long)]
+        decimals: Option<u8>,
+        /// Mint to pay in [default: the accounting's primary mint]
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Price feed converting --amount from a quote currency into tokens
+        /// at every payment
+        #[arg(long, requires = "quote_decimals")]
+        price_feed: Option<Pubkey>,
+        /// Decimals of the quote currency --amount is expressed in
+        #[arg(long, requires = "price_feed")]
+        quote_decimals: Option<u8>,
+        /// Oldest price, in seconds, a payment accepts
+        #[arg(long, default_value_t = 60)]
+        max_price_age: u64,
+        /// Widest confidence interval, in basis points of the price, a payment accepts
+        #[arg(long, default_value_t = 100)]
+        max_confidence_bps: u16,
+        /// Largest move, in basis points, from the previously applied price
+        #[arg(long, default_value_t = 1_000)]
+        max_slippage_bps: u16,
+        /// Create the payroll even if the vault can't cover everything its
+        /// payrolls are committed to
+        #[arg(long)]
+        allow_overcommit: bool,
+        /// Also start paying the payroll on its schedule, in the same transaction
+        #[arg(long)]
+        start: bool,
+        /// Unix timestamp before which cycles pay nothing, e.g. a new hire's first day
+        #[arg(long)]
+        start_ts: Option<i64>,
+        /// Unix timestamp from which the payroll stops paying
+        #[arg(long)]
+        end_ts: Option<i64>,
+        /// Pay the cycles --start-ts and --end-ts fall within for the part of their period
+        /// they cover
+        #[arg(long)]
+        prorate: bool,
+    },
+    /// Change the amount or number of cycles of a payroll
+    Update {
+        receiver: Pubkey,
+        #[arg(long)]
+        amount: Option<u64>,
+        #[arg(long)]
+        cycles: Option<u64>,
+        /// Remove the cycle limit, paying until the payroll is cancelled
+        #[arg(long, conflicts_with = "cycles")]
+        open_ended: bool,
+        /// Update the payroll even if the vault can't cover everything its
+        /// payrolls are committed to afterwards
+        #[arg(long)]
+        allow_overcommit: bool,
+    },
+    /// Add a one-off bonus, or a deduction if negative, to a payroll's next payment
+    Adjust {
+        receiver: Pubkey,
+        #[arg(allow_hyphen_values = true)]
+        amount: i64,
+        /// Reason for the adjustment, at most 32 bytes
+        #[arg(long, default_value = "")]
+        memo: String,
+    },
+    /// Accept a quote-denominated payroll's current feed price as the one later payments
+    /// measure slippage against
+    Reprice { receiver: Pubkey },
+    /// Start paying a payroll on its schedule
+    ///
+    /// Payments go to the receiver's associated token account, which the first payment
+    /// creates out of the rent budget if it doesn't exist
+    Start { receiver: Pubkey },
+    /// Have a payroll paying the keypair pay into another of its token accounts
+    SetPayoutAccount {
+        payout_account: Pubkey,
+        /// Authority of the accounting that pays the keypair
+        #[arg(long)]
+        authority: Pubkey,
+    },
+    /// Move a payroll paying the keypair to a new receiver wallet
+    Transfer {
+        new_receiver: Pubkey,
+        /// Authority of the accounting that pays the keypair
+        #[arg(long)]
+        authority: Pubkey,
+        /// Keypair of the authority, for accountings that require approval
+        #[arg(long)]
+        approver: Option<String>,
+    },
+    /// Have part of the keypair's next payments paid now, repaid out of them
+    Advance {
+        amount: u64,
+        /// Keypair of the authority of the accounting that pays the keypair
+        #[arg(long)]
+        approver: String,
+    },
+    /// Pause a started payroll
+    Pause { receiver: Pubkey },
+    /// Resume a paused payroll
+    Resume { receiver: Pubkey },
+    /// Stop a payroll and close its account
+    Cancel {
+        receiver: Pubkey,
+        /// Cancel even if an advance hasn't been repaid, writing off the rest of it
+        #[arg(long)]
+        write_off: bool,
+    },
+    /// Create payrolls from a CSV file of `receiver,amount,schedule,cycles` rows
+    ///
+    /// Leave cycles empty or set it to `open-ended` for an open-ended payroll, and quote
+    /// schedules that contain commas, e.g. "0 0 9 1,15 * * *"
+    Import {
+        file: String,
+        /// Mint the payrolls pay in [default: the accounting's primary mint]
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Also start every payroll's thread
+        #[arg(long)]
+        start: bool,
+        /// Import even if the vault can't cover everything its payrolls are committed to
+        #[arg(long)]
+        allow_overcommit: bool,
+        /// Only validate the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List all payrolls of an accounting
+    List {
+        /// Authority of the accounting [default: the keypair]
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+    /// Show a single payroll and its recent receipts
+    Show {
+        receiver: Pubkey,
+        /// Authority of the accounting [default: the keypair]
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+}
+
+#[derive(Clone)]
+struct DeductionSpec {
+    args: DeductionArgs,
+    destination: Pubkey,
+}
+
+impl FromStr for DeductionSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let [kind, value, destination] = parts[..] else {
+            return Err("expected <fixed|bps>:<value>:<destination>".to_string());
+        };
+        let kind = match kind {
+            "fixed" => DeductionKind::Fixed,
+            "bps" => DeductionKind::BasisPoints,
+            _ => return Err(format!("unknown deduction kind `{}`", kind)),
+        };
+        let value = value.parse().map_err(|e| format!("invalid value: {}", e))?;
+        let destination = destination
+            .parse()
+            .map_err(|e| format!("invalid destination: {}", e))?;
+
+        Ok(Self {
+            args: DeductionArgs { kind, value },
+            destination,
+        })
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let config = CONFIG_FILE
+        .as_ref()
+        .and_then(|path| Config::load(path).ok())
+        .unwrap_or_default();
+    let url = normalize_url(cli.url.as_deref().unwrap_or(&config.json_rpc_url));
+    let keypair_path = cli.keypair.unwrap_or(config.keypair_path);
+    let keypair = read_keypair_file(&keypair_path)
+        .map_err(|e| format!("failed to read keypair {}: {}", keypair_path, e))?;
+
+    let rpc = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+    let client = ClockPayClient::new(rpc, cli.program_id);
+    let output = cli.output;
+
+    match cli.command {
+        Command::InitAccounting {
+            mint,
+            treasury,
+            allowance,
+        } => {
+            let signature = match (treasury, allowance) {
+                (Some(treasury), Some(allowance)) => {
+                    client.create_delegated_accounting(&keypair, &mint, &treasury, allowance)?
+                }
+                _ => client.create_accounting(&keypair, &mint)?,
+            };
+            output.signature(&signature);
+        }
+        Command::SetAllowance { allowance } => {
+            let signature = client.set_allowance(&keypair, allowance)?;
+            output.signature(&signature);
+        }
+        Command::AddMint { mint } => {
+            let signature = client.add_mint(&keypair, &mint)?;
+            output.signature(&signature);
+        }
+        Command::FundRentBudget { lamports } => {
+            let signature = client.fund_rent_budget(&keypair, lamports)?;
+            output.signature(&signature);
+        }
+        Command::FundFeeReserve { lamports } => {
+            let signature = client.fund_fee_reserve(&keypair, lamports)?;
+            output.signature(&signature);
+        }
+        Command::WithdrawRentBudget { lamports } => {
+            let signature = client.withdraw_rent_budget(&keypair, lamports)?;
+            output.signature(&signature);
+        }
+        Command::WithdrawFeeReserve { lamports } => {
+            let signature = client.withdraw_fee_reserve(&keypair, lamports)?;
+            output.signature(&signature);
+        }
+        Command::SetThreadMinBalance { lamports } => {
+            let signature = client.set_thread_min_balance(&keypair, lamports)?;
+            output.signature(&signature);
+        }
+        Command::SetTransferApproval { required } => {
+            let signature = client.set_transfer_approval(&keypair, required)?;
+            output.signature(&signature);
+        }
+        Command::SetAdvanceLimit { max_cycles } => {
+            let signature = client.set_advance_limit(&keypair, max_cycles)?;
+            output.signature(&signature);
+        }
+        Command::FundThreads { authority } => {
+            let authority = authority.unwrap_or_else(|| keypair.pubkey());
+            let accounting = client.accounting_address(&authority);
+            match client.fund_threads(&keypair, &accounting)? {
+                Some(signature) => output.signature(&signature),
+                None => println!("No active payrolls"),
+            }
+        }
+        Command::Sync { authority, mint } => {
+            let authority = authority.unwrap_or_else(|| keypair.pubkey());
+            let accounting = client.accounting_address(&authority);
+            let signature = client.sync(&keypair, &accounting, mint.as_ref())?;
+            output.signature(&signature);
+        }
+        Command::ShowAccounting { authority } => {
+            let authority = authority.unwrap_or_else(|| keypair.pubkey());
+            let address = client.accounting_address(&authority);
+            let mint_vaults = client.get_mint_vaults(&address)?;
+            let rent_budget = client.get_rent_budget(&address)?;
+            let accounting = client.get_accounting(&address)?;
+            let mut runways = vec![(accounting.mint, client.get_runway(&address, None)?)];
+            for (_, mint_vault) in &mint_vaults {
+                let runway = client.get_runway(&address, Some(&mint_vault.mint))?;
+                runways.push((mint_vault.mint, runway));
+            }
+            output.accounting(&address, &accounting, &mint_vaults, rent_budget, &runways);
+        }
+        Command::Deposit {
+            amount,
+            mint,
+            source,
+            sol,
+        } => {
+            let source = match source {
+                Some(source) => source,
+                None if sol => keypair.pubkey(),
+                None => own_token_account(&client, &keypair, mint.as_ref())?,
+            };
+            let signature = client.deposit(&keypair, mint.as_ref(), &source, amount)?;
+            output.signature(&signature);
+        }
+        Command::Withdraw {
+            amount,
+            mint,
+            destination,
+            allow_overcommit,
+        } => {
+            let destination = destination
+                .map_or_else(|| own_token_account(&client, &keypair, mint.as_ref()), Ok)?;
+            let args = WithdrawArgs {
+                amount,
+                allow_overcommit,
+            };
+            let signature = client.withdraw(&keypair, mint.as_ref(), &destination, &args)?;
+            output.signature(&signature);
+        }
+        Command::Payroll { command } => run_payroll(&client, &keypair, output, command)?,
+    }
+
+    Ok(())
+}
+
+fn run_payroll(
+    client: &ClockPayClient,
+    keypair: &Keypair,
+    output: OutputFormat,
+    command: PayrollCommand,
+) -> CliResult<()> {
+    match command {
+        PayrollCommand::Create {
+            receiver,
+            amount,
+            cycles,
+            open_ended,
+            schedule,
+            deductions,
+            decimals,
+            mint,
+            price_feed,
+            quote_decimals,
+            max_price_age,
+            max_confidence_bps,
+            max_slippage_bps,
+            allow_overcommit,
+            start,
+            start_ts,
+            end_ts,
+            prorate,
+        } => {
+            let schedule = Payroll::pack_schedule(&schedule).ok_or_else(|| {
+                format!("schedule must be at most {} bytes", Payroll::SCHEDULE_LEN)
+            })?;
+            let destinations: Vec<Pubkey> = deductions.iter().map(|d| d.destination).collect();
+            let args = NewPayrollArgs {
+                amount,
+                cycles: cycles_arg(cycles, open_ended).unwrap_or(Payroll::OPEN_ENDED),
+                schedule,
+                withholding: deductions.into_iter().map(|d| d.args).collect(),
+                decimals,
+                mint,
+                quote: quote_decimals.map(|quote_decimals| QuoteArgs {
+                    quote_decimals,
+                    max_price_age,
+                    max_confidence_bps,
+                    max_slippage_bps,
+                }),
+                allow_overcommit,
+                start_ts,
+                end_ts,
+                prorate,
+            };
+            let signature = if start {
+                client.create_and_start_payroll(
+                    keypair,
+                    &receiver,
+                    &args,
+                    price_feed.as_ref(),
+                    &destinations,
+                )?
+            } else {
+                client.create_payroll(
+                    keypair,
+                    &receiver,
+                    &args,
+                    price_feed.as_ref(),
+                    &destinations,
+                )?
+            };
+            output.signature(&signature);
+        }
+        PayrollCommand::Update {
+            receiver,
+            amount,
+            cycles,
+            open_ended,
+            allow_overcommit,
+        } => {
+            let args = UpdatePayrollArgs {
+                amount,
+                cycles: cycles_arg(cycles, open_ended),
+                allow_overcommit,
+            };
+            let signature = client.update_payroll(keypair, &receiver, &args)?;
+            output.signature(&signature);
+        }
+        PayrollCommand::Adjust {
+            receiver,
+            amount,
+            memo,
+        } => {
+            let memo = Adjustment::pack_memo(&memo)
+                .ok_or_else(|| format!("memo must be at most {} bytes", Adjustment::MEMO_LEN))?;
+            let args = AdjustmentArgs { amount, memo };
+            let signature = client.add_adjustment(keypair, &receiver, &args)?;
+            output.signature(&signature);
+        }
+        PayrollCommand::Reprice { receiver } => {
+            let signature = client.reprice_payroll(keypair, &receiver)?;
+            output.signature(&signature);
+        }
+        PayrollCommand::Start { receiver } => {
+            let signature = client.start_payment(keypair, &receiver)?;
+            output.signature(&signature);
+        }
+        PayrollCommand::SetPayoutAccount {
+            payout_account,
+            authority,
+        } => {
+            let signature = client.set_payout_account(keypair, &authority, &payout_account)?;
+            output.signature(&signature);
+        }
+        PayrollCommand::Transfer {
+            new_receiver,
+            authority,
+            approver,
+        } => {
+            let approver = approver
+                .map(|path| {
+                    read_keypair_file(&path)
+                        .map_err(|e| format!("failed to read keypair {}: {}", path, e))
+                })
+                .transpose()?;
+            let signature =
+                client.transfer_payroll(keypair, &authority, &new_receiver, approver.as_ref())?;
+            output.signature(&signature);
+        }
+        PayrollCommand::Advance { amount, approver } => {
+            let approver = read_keypair_file(&approver)
+                .map_err(|e| format!("failed to read keypair {}: {}", approver, e))?;
+            let signature = client.request_advance(keypair, &approver, amount)?;
+            output.signature(&signature);
+        }
+        PayrollCommand::Pause { receiver } => {
+            output.signature(&client.pause_payroll(keypair, &receiver)?);
+        }
+        PayrollCommand::Resume { receiver } => {
+            output.signature(&client.resume_payroll(keypair, &receiver)?);
+        }
+        PayrollCommand::Cancel {
+            receiver,
+            write_off,
+        } => {
+            output.signature(&client.cancel_payroll(keypair, &receiver, write_off)?);
+        }
+        PayrollCommand::Import {
+            file,
+            mint,
+            start,
+            allow_overcommit,
+            dry_run,
+        } => {
+            let options = ImportOptions {
+                mint,
+                start,
+                allow_overcommit,
+                dry_run,
+            };
+            let reports = import::import(client, keypair, &file, &options)?;
+            output.import(&reports);
+            if reports.iter().any(|report| report.outcome.is_err()) {
+                return Err("some rows were not imported".into());
+            }
+        }
+        PayrollCommand::List { authority } => {
+            let authority = authority.unwrap_or_else(|| keypair.pubkey());
+            let payrolls = client.get_payrolls(&client.accounting_address(&authority))?;
+            output.payrolls(&payrolls);
+        }
+        PayrollCommand::Show {
+            receiver,
+            authority,
+        } => {
+            let authority = authority.unwrap_or_else(|| keypair.pubkey());
+            let address = client.payroll_address(&authority, &receiver);
+            let payroll = client.get_payroll(&address)?;
+            // A payroll that was never started has no payment history.
+            let receipts = match client.get_receipts(&address) {
+                Err(ClientError::AccountNotFound(_)) => vec![],
+                receipts => receipts?,
+            };
+            output.payroll(&address, &payroll, &receipts);
+        }
+    }
+
+    Ok(())
+}
+
+/// The keypair's associated token account for `mint`, or for its
+/// accounting's primary mint if `None`.
+fn own_token_account(
+    client: &ClockPayClient,
+    keypair: &Keypair,
+    mint: Option<&Pubkey>,
+) -> CliResult<Pubkey> {
+    let accounting = client.accounting_address(&keypair.pubkey());
+    let (_, mint_vault) = client.get_mint_vault(&accounting, mint)?;
+    Ok(get_associated_token_address_with_program_id(
+        &keypair.pubkey(),
+        &mint_vault.mint,
+        &mint_vault.token_program,
+    ))
+}
+
+/// Expands the same URL monikers the Solana CLI accepts.
+fn normalize_url(url: &str) -> String {
+    match url {
+        "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
+        "t" | "testnet" => "https://api.testnet.solana.com",
+        "d" | "devnet" => "https://api.devnet.solana.com",
+        "l" | "localhost" => "http://localhost:8899",
+        url => url,
+    }
+    .to_string()
+}
+
+/// The `max_cycles` a `--cycles`/`--open-ended` pair asks for, if any.
+fn cycles_arg(cycles: Option<u64>, open_ended: bool) -> Option<u64> {
+    if open_ended {
+        Some(Payroll::OPEN_ENDED)
+    } else {
+        cycles
+    }
+}
@@ -0,0 +1,391 @@
+use crate::import::RowReport;
+use clap::ValueEnum;
+use program::state::{Accounting, MintVault, Payroll, Receipt};
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Display,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn signature(self, signature: &Signature) {
+        match self {
+            Self::Display => println!("Signature: {}", signature),
+            Self::Json => println!("{}", json!({ "signature": signature.to_string() })),
+        }
+    }
+
+    pub fn accounting(
+        self,
+        address: &Pubkey,
+        accounting: &Accounting,
+        mint_vaults: &[(Pubkey, MintVault)],
+        rent_budget: u64,
+        runways: &[(Pubkey, u64)],
+    ) {
+        let runway = |mint: &Pubkey| {
+            runways
+                .iter()
+                .find(|(runway_mint, _)| runway_mint == mint)
+                .map_or(u64::MAX, |&(_, runway)| runway)
+        };
+        match self {
+            Self::Display => {
+                println!("Accounting: {}", address);
+                println!("  Authority:       {}", accounting.authority);
+                println!("  Mint:            {}", accounting.mint);
+                println!("  Token program:   {}", accounting.token_program);
+                println!("  Decimals:        {}", accounting.decimals);
+                println!("  Vault:           {}", accounting.vault);
+                println!("  Balance:         {}", accounting.balance);
+                println!(
+                    "  Committed:       {}{}",
+                    accounting.committed_amount,
+                    if accounting.balance < accounting.committed_amount {
+                        " (overcommitted)"
+                    } else {
+                        ""
+                    }
+                );
+                println!(
+                    "  Runway:          {}",
+                    display_runway(runway(&accounting.mint))
+                );
+                println!("  Active payrolls: {}", accounting.active_payrolls);
+                println!("  Active:          {}", accounting.active);
+                println!("  Funding mode:    {:?}", accounting.funding_mode);
+                println!("  Rent budget:     {} lamports", rent_budget);
+                println!(
+                    "  Fee reserve:     {} lamports{}",
+                    accounting.fee_reserve,
+                    if accounting.fee_reserve_low() {
+                        " (low)"
+                    } else {
+                        ""
+                    }
+                );
+                println!(
+                    "  Thread minimum:  {} lamports",
+                    accounting.thread_min_balance
+                );
+                println!(
+                    "  Transfers:       {}",
+                    if accounting.transfer_approval {
+                        "need the authority's approval"
+                    } else {
+                        "receiver only"
+                    }
+                );
+                println!(
+                    "  Advance limit:   {} cycles",
+                    accounting.max_advance_cycles
+                );
+                for (_, mint_vault) in mint_vaults {
+                    println!(
+                        "  Mint vault:      {} in {} (balance {}, committed {}, runway {})",
+                        mint_vault.mint,
+                        mint_vault.vault,
+                        mint_vault.balance,
+                        mint_vault.committed_amount,
+                        display_runway(runway(&mint_vault.mint))
+                    );
+                }
+            }
+            Self::Json => {
+                let mut value = accounting_json(address, accounting);
+                value["rentBudget"] = rent_budget.into();
+                value["runway"] = runway_json(runway(&accounting.mint));
+                value["mintVaults"] = mint_vaults
+                    .iter()
+                    .map(|(record, mint_vault)| {
+                        json!({
+                            "address": record.to_string(),
+                            "mint": mint_vault.mint.to_string(),
+                            "tokenProgram": mint_vault.token_program.to_string(),
+                            "decimals": mint_vault.decimals,
+                            "vault": mint_vault.vault.to_string(),
+                            "balance": mint_vault.balance,
+                            "committedAmount": mint_vault.committed_amount,
+                            "solvent": mint_vault.is_solvent(),
+                            "runway": runway_json(runway(&mint_vault.mint)),
+                        })
+                    })
+                    .collect();
+                println!("{}", value);
+            }
+        }
+    }
+
+    pub fn payroll(self, address: &Pubkey, payroll: &Payroll, receipts: &[Receipt]) {
+        match self {
+            Self::Display => {
+                display_payroll(address, payroll);
+                if !receipts.is_empty() {
+                    println!("  Receipts:");
+                }
+                for receipt in receipts {
+                    println!(
+                        "    cycle {:>4}  ts {}  gross {}  withheld {:?}  adjustment {}  to {}",
+                        receipt.cycle,
+                        receipt.timestamp,
+                        receipt.gross_amount,
+                        &receipt.deductions[..payroll.deduction_count as usize],
+                        receipt.adjustment,
+                        receipt.receiver_wallet,
+                    );
+                }
+            }
+            Self::Json => {
+                let mut value = payroll_json(address, payroll);
+                value["receipts"] = receipts
+                    .iter()
+                    .map(|receipt| {
+                        json!({
+                            "cycle": receipt.cycle,
+                            "timestamp": receipt.timestamp,
+                            "grossAmount": receipt.gross_amount,
+                            "deductions": &receipt.deductions[..payroll.deduction_count as usize],
+                            "adjustment": receipt.adjustment,
+                            "receiverWallet": receipt.receiver_wallet.to_string(),
+                            "thread": receipt.thread.to_string(),
+                        })
+                    })
+                    .collect();
+                println!("{}", value);
+            }
+        }
+    }
+
+    pub fn import(self, reports: &[RowReport]) {
+        match self {
+            Self::Display => {
+                for report in reports {
+                    let receiver = report
+                        .receiver
+                        .map_or_else(|| "-".to_string(), |receiver| receiver.to_string());
+                    match &report.outcome {
+                        Ok(Some(signature)) => {
+                            println!(
+                                "line {:>4}  {}  created in {}",
+                                report.line, receiver, signature
+                            )
+                        }
+                        Ok(None) => println!("line {:>4}  {}  valid", report.line, receiver),
+                        Err(error) => {
+                            println!("line {:>4}  {}  failed: {}", report.line, receiver, error)
+                        }
+                    }
+                }
+            }
+            Self::Json => {
+                let values: Vec<Value> = reports
+                    .iter()
+                    .map(|report| {
+                        json!({
+                            "line": report.line,
+                            "receiver": report.receiver.map(|receiver| receiver.to_string()),
+                            "signature": report
+                                .outcome
+                                .as_ref()
+                                .ok()
+                                .and_then(Option::as_ref)
+                                .map(|signature| signature.to_string()),
+                            "error": report.outcome.as_ref().err(),
+                        })
+                    })
+                    .collect();
+                println!("{}", Value::Array(values));
+            }
+        }
+    }
+
+    pub fn payrolls(self, payrolls: &[(Pubkey, Payroll)]) {
+        match self {
+            Self::Display => {
+                if payrolls.is_empty() {
+                    println!("No payrolls found");
+                }
+                for (address, payroll) in payrolls {
+                    display_payroll(address, payroll);
+                }
+            }
+            Self::Json => {
+                let values: Vec<Value> = payrolls
+                    .iter()
+                    .map(|(address, payroll)| payroll_json(address, payroll))
+                    .collect();
+                println!("{}", Value::Array(values));
+            }
+        }
+    }
+}
+
+fn display_payroll(address: &Pubkey, payroll: &Payroll) {
+    println!("Payroll: {}", address);
+    println!("  Receiver:        {}", payroll.receiver);
+    println!("  Mint:            {}", payroll.mint);
+    println!("  Active:          {}", payroll.active);
+    println!("  Amount:          {}", payroll.amount);
+    println!("  Schedule:        {}", payroll.schedule());
+    if payroll.is_open_ended() {
+        println!(
+            "  Cycles:          {} (open-ended)",
+            payroll.cycles_completed
+        );
+    } else {
+        println!(
+            "  Cycles:          {}/{}",
+            payroll.cycles_completed, payroll.max_cycles
+        );
+    }
+    println!("  Total disbursed: {}", payroll.total_amount_disbursed);
+    println!("  Committed:       {}", payroll.committed);
+    if payroll.payout_account != Pubkey::default() {
+        println!("  Payout account:  {}", payroll.payout_account);
+    }
+    if payroll.advance_outstanding > 0 {
+        println!("  Advance owed:    {}", payroll.advance_outstanding);
+    }
+    for adjustment in payroll.pending_adjustments() {
+        println!(
+            "  Adjustment:      {:+} {}",
+            adjustment.amount,
+            adjustment.memo()
+        );
+    }
+    if payroll.start_ts != 0 {
+        println!("  Starts at:       {}", payroll.start_ts);
+    }
+    if payroll.end_ts != 0 {
+        println!("  Ends at:         {}", payroll.end_ts);
+    }
+    if payroll.prorate {
+        println!("  Pro-rated:       first and last cycles");
+    }
+    println!("  Thread:          {}", payroll.thread);
+    if payroll.pricing.is_enabled() {
+        let pricing = &payroll.pricing;
+        println!(
+            "  Quote pricing:   feed {} ({} decimals, max age {}s, confidence {} bps, slippage {} bps)",
+            pricing.price_feed,
+            pricing.quote_decimals,
+            pricing.max_price_age,
+            pricing.max_confidence_bps,
+            pricing.max_slippage_bps
+        );
+        println!(
+            "  Last price:      {}e{}",
+            pricing.last_price, pricing.last_expo
+        );
+    }
+    for deduction in payroll.active_deductions() {
+        println!(
+            "  Deduction:       {:?} {} to {} (withheld {})",
+            deduction.kind, deduction.value, deduction.destination, deduction.total_withheld
+        );
+    }
+}
+
+/// A runway of `u64::MAX` cycles means the balance outlasts every payroll.
+fn display_runway(runway: u64) -> String {
+    match runway {
+        u64::MAX => "covers all payrolls".to_string(),
+        cycles => format!("{} cycles", cycles),
+    }
+}
+
+fn runway_json(runway: u64) -> Value {
+    match runway {
+        u64::MAX => Value::Null,
+        cycles => cycles.into(),
+    }
+}
+
+fn accounting_json(address: &Pubkey, accounting: &Accounting) -> Value {
+    json!({
+        "address": address.to_string(),
+        "authority": accounting.authority.to_string(),
+        "mint": accounting.mint.to_string(),
+        "tokenProgram": accounting.token_program.to_string(),
+        "decimals": accounting.decimals,
+        "vault": accounting.vault.to_string(),
+        "balance": accounting.balance,
+        "committedAmount": accounting.committed_amount,
+        "solvent": accounting.balance >= accounting.committed_amount,
+        "activePayrolls": accounting.active_payrolls,
+        "active": accounting.active,
+        "fundingMode": format!("{:?}", accounting.funding_mode),
+        "feeReserve": accounting.fee_reserve,
+        "feeReserveLow": accounting.fee_reserve_low(),
+        "threadMinBalance": accounting.thread_min_balance,
+        "transferApproval": accounting.transfer_approval,
+        "maxAdvanceCycles": accounting.max_advance_cycles,
+    })
+}
+
+fn payroll_json(address: &Pubkey, payroll: &Payroll) -> Value {
+    let deductions: Vec<Value> = payroll
+        .active_deductions()
+        .iter()
+        .map(|deduction| {
+            json!({
+                "kind": format!("{:?}", deduction.kind),
+                "value": deduction.value,
+                "destination": deduction.destination.to_string(),
+                "totalWithheld": deduction.total_withheld,
+            })
+        })
+        .collect();
+    let adjustments: Vec<Value> = payroll
+        .pending_adjustments()
+        .iter()
+        .map(|adjustment| {
+            json!({
+                "amount": adjustment.amount,
+                "memo": adjustment.memo(),
+            })
+        })
+        .collect();
+    let pricing = &payroll.pricing;
+    let quote_pricing = if pricing.is_enabled() {
+        json!({
+            "priceFeed": pricing.price_feed.to_string(),
+            "quoteDecimals": pricing.quote_decimals,
+            "maxPriceAge": pricing.max_price_age,
+            "maxConfidenceBps": pricing.max_confidence_bps,
+            "maxSlippageBps": pricing.max_slippage_bps,
+            "lastPrice": pricing.last_price,
+            "lastExpo": pricing.last_expo,
+        })
+    } else {
+        Value::Null
+    };
+
+    json!({
+        "address": address.to_string(),
+        "accounting": payroll.accounting.to_string(),
+        "receiver": payroll.receiver.to_string(),
+        "mint": payroll.mint.to_string(),
+        "active": payroll.active,
+        "amount": payroll.amount,
+        "schedule": payroll.schedule(),
+        "maxCycles": (!payroll.is_open_ended()).then_some(payroll.max_cycles),
+        "openEnded": payroll.is_open_ended(),
+        "cyclesCompleted": payroll.cycles_completed,
+        "totalAmountDisbursed": payroll.total_amount_disbursed,
+        "committed": payroll.committed,
+        "startTs": (payroll.start_ts != 0).then_some(payroll.start_ts),
+        "endTs": (payroll.end_ts != 0).then_some(payroll.end_ts),
+        "prorate": payroll.prorate,
+        "payoutAccount": (payroll.payout_account != Pubkey::default())
+            .then(|| payroll.payout_account.to_string()),
+        "advanceOutstanding": payroll.advance_outstanding,
+        "adjustments": adjustments,
+        "deductions": deductions,
+        "quotePricing": quote_pricing,
+        "thread": payroll.thread.to_string(),
+    })
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error(transparent)]
+    Rpc(#[from] solana_client::client_error::ClientError),
+    #[error("Failed to decode account {0}: {1}")]
+    InvalidAccountData(solana_sdk::pubkey::Pubkey, std::io::Error),
+    #[error("Account {0} not found")]
+    AccountNotFound(solana_sdk::pubkey::Pubkey),
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,717 @@
+//! Off-chain client for the clockpay program.
+//!
+//! Wraps a [`ClockPayRpc`], a `solana_client` [`RpcClient`] by default, with
+//! typed helpers for sending clockpay instructions and fetching and decoding
+//! its accounts. The instructions themselves come from
+//! `program::instruction`, so the client always matches the on-chain layout.
+
+pub mod error;
+pub mod rpc;
+
+use borsh::BorshDeserialize;
+use program::{
+    events::{self, ClockPayEvent},
+    instruction::{
+        self, AdjustmentArgs, NewPayrollArgs, NewPayrollBatchArgs, UpdatePayrollArgs, WithdrawArgs,
+    },
+    state::{self, Accounting, MintVault, PaymentHistory, Payroll, Receipt},
+};
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    account::from_account,
+    clock::Clock,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+
+use std::{collections::HashMap, io, ops::Range};
+
+pub use error::{ClientError, Result};
+pub use rpc::ClockPayRpc;
+
+/// Payrolls `create_payroll_batch` sends per transaction, keeping each under
+/// the transaction size and compute limits.
+pub const PAYROLL_BATCH_SIZE: usize = 4;
+/// Payrolls per transaction when `create_payroll_batch` also starts them.
+pub const STARTED_PAYROLL_BATCH_SIZE: usize = 2;
+
+/// A payroll to create with `create_payroll_batch`, with the accounts
+/// `create_payroll` takes alongside its arguments.
+#[derive(Clone, Debug)]
+pub struct BatchPayroll {
+    pub receiver: Pubkey,
+    pub args: NewPayrollArgs,
+    pub price_feed: Option<Pubkey>,
+    pub deduction_destinations: Vec<Pubkey>,
+}
+
+pub struct ClockPayClient<R = RpcClient> {
+    rpc: R,
+    program_id: Pubkey,
+}
+
+impl<R: ClockPayRpc> ClockPayClient<R> {
+    pub fn new(rpc: R, program_id: Pubkey) -> Self {
+        Self { rpc, program_id }
+    }
+
+    pub fn rpc(&self) -> &R {
+        &self.rpc
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    pub fn accounting_address(&self, authority: &Pubkey) -> Pubkey {
+        Accounting::find_address(&self.program_id, authority).0
+    }
+
+    pub fn payroll_address(&self, authority: &Pubkey, receiver: &Pubkey) -> Pubkey {
+        let accounting = self.accounting_address(authority);
+        Payroll::find_address(&self.program_id, &accounting, receiver).0
+    }
+
+    pub fn create_accounting(&self, authority: &Keypair, mint: &Pubkey) -> Result<Signature> {
+        let token_program = self.mint_token_program(mint)?;
+        let ix = instruction::init_accounting(
+            &self.program_id,
+            &authority.pubkey(),
+            mint,
+            &token_program,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Creates an accounting that pays out of `treasury`, a token account of
+    /// the authority's, through a delegate allowance instead of a vault.
+    pub fn create_delegated_accounting(
+        &self,
+        authority: &Keypair,
+        mint: &Pubkey,
+        treasury: &Pubkey,
+        allowance: u64,
+    ) -> Result<Signature> {
+        let token_program = self.mint_token_program(mint)?;
+        let ix = instruction::init_delegated_accounting(
+            &self.program_id,
+            &authority.pubkey(),
+            mint,
+            &token_program,
+            treasury,
+            allowance,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    pub fn set_allowance(&self, authority: &Keypair, allowance: u64) -> Result<Signature> {
+        let accounting = self.get_accounting(&self.accounting_address(&authority.pubkey()))?;
+        let ix = instruction::set_allowance(
+            &self.program_id,
+            &authority.pubkey(),
+            &accounting.token_program,
+            &accounting.vault,
+            allowance,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Adds a vault for `mint` to the authority's accounting.
+    pub fn add_mint(&self, authority: &Keypair, mint: &Pubkey) -> Result<Signature> {
+        let token_program = self.mint_token_program(mint)?;
+        let ix = instruction::add_mint(&self.program_id, &authority.pubkey(), mint, &token_program);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Deposits into the vault of `mint`, or of the primary mint if `None`.
+    /// A `source` of the authority itself deposits lamports into a native
+    /// mint vault.
+    pub fn deposit(
+        &self,
+        authority: &Keypair,
+        mint: Option<&Pubkey>,
+        source: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let (record, mint_vault) = self.get_mint_vault(&accounting, mint)?;
+        let ix = instruction::deposit(
+            &self.program_id,
+            &authority.pubkey(),
+            &mint_vault.mint,
+            &mint_vault.token_program,
+            record.as_ref(),
+            source,
+            amount,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    pub fn create_payroll(
+        &self,
+        authority: &Keypair,
+        receiver: &Pubkey,
+        args: &NewPayrollArgs,
+        price_feed: Option<&Pubkey>,
+        deduction_destinations: &[Pubkey],
+    ) -> Result<Signature> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let (record, _) = self.get_mint_vault(&accounting, args.mint.as_ref())?;
+        let ix = instruction::new_payroll(
+            &self.program_id,
+            &authority.pubkey(),
+            receiver,
+            args,
+            record.as_ref(),
+            price_feed,
+            deduction_destinations,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Creates a payroll and starts its thread in a single transaction.
+    pub fn create_and_start_payroll(
+        &self,
+        authority: &Keypair,
+        receiver: &Pubkey,
+        args: &NewPayrollArgs,
+        price_feed: Option<&Pubkey>,
+        deduction_destinations: &[Pubkey],
+    ) -> Result<Signature> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let (record, mint_vault) = self.get_mint_vault(&accounting, args.mint.as_ref())?;
+        let ix = instruction::create_and_start_payroll(
+            &self.program_id,
+            &authority.pubkey(),
+            receiver,
+            args,
+            &mint_vault.mint,
+            &mint_vault.vault,
+            &mint_vault.token_program,
+            record.as_ref(),
+            price_feed,
+            deduction_destinations,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Creates `payrolls`, and starts them if `start` is set, in as many
+    /// `NewPayrollBatch` transactions as it takes. Each transaction succeeds or
+    /// fails as a whole, so the result of each covers a range of `payrolls`.
+    pub fn create_payroll_batch(
+        &self,
+        authority: &Keypair,
+        payrolls: &[BatchPayroll],
+        start: bool,
+    ) -> Result<Vec<(Range<usize>, Result<Signature>)>> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let mut mint_vaults = HashMap::new();
+        let mut payroll_accounts = Vec::with_capacity(payrolls.len());
+        for payroll in payrolls {
+            let mint = payroll.args.mint;
+            if !mint_vaults.contains_key(&mint) {
+                mint_vaults.insert(mint, self.get_mint_vault(&accounting, mint.as_ref())?);
+            }
+            let (record, mint_vault) = &mint_vaults[&mint];
+            let mut accounts = instruction::new_payroll(
+                &self.program_id,
+                &authority.pubkey(),
+                &payroll.receiver,
+                &payroll.args,
+                record.as_ref(),
+                payroll.price_feed.as_ref(),
+                &payroll.deduction_destinations,
+            )
+            .accounts;
+            if start {
+                accounts.extend(
+                    instruction::init_payment(
+                        &self.program_id,
+                        &authority.pubkey(),
+                        &mint_vault.mint,
+                        &mint_vault.vault,
+                        &mint_vault.token_program,
+                        record.as_ref(),
+                        &payroll.receiver,
+                        None,
+                    )
+                    .accounts,
+                );
+            }
+            payroll_accounts.push(accounts);
+        }
+
+        let batch_size = if start {
+            STARTED_PAYROLL_BATCH_SIZE
+        } else {
+            PAYROLL_BATCH_SIZE
+        };
+        let mut results = vec![];
+        for first in (0..payrolls.len()).step_by(batch_size) {
+            let rows = first..payrolls.len().min(first + batch_size);
+            let args = NewPayrollBatchArgs {
+                payrolls: payrolls[rows.clone()]
+                    .iter()
+                    .map(|payroll| payroll.args.clone())
+                    .collect(),
+                start,
+            };
+            let accounts = payroll_accounts[rows.clone()].concat();
+            let ix = instruction::new_payroll_batch(&self.program_id, &args, accounts);
+            results.push((rows, self.send(&[ix], authority, &[])));
+        }
+        Ok(results)
+    }
+
+    /// Starts a payroll's thread. Payments go to the receiver's associated
+    /// token account, which the first payment creates if it doesn't exist.
+    pub fn start_payment(&self, authority: &Keypair, receiver: &Pubkey) -> Result<Signature> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let payroll = self.get_payroll(&self.payroll_address(&authority.pubkey(), receiver))?;
+        let (record, mint_vault) = self.get_mint_vault(&accounting, Some(&payroll.mint))?;
+        let payout_account = Some(payroll.payout_account).filter(|key| *key != Pubkey::default());
+        let ix = instruction::init_payment(
+            &self.program_id,
+            &authority.pubkey(),
+            &mint_vault.mint,
+            &mint_vault.vault,
+            &mint_vault.token_program,
+            record.as_ref(),
+            receiver,
+            payout_account.as_ref(),
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Tops up the lamports the accounting spends creating receivers' token
+    /// accounts.
+    pub fn fund_rent_budget(&self, authority: &Keypair, lamports: u64) -> Result<Signature> {
+        let ix = instruction::fund_rent_budget(&self.program_id, &authority.pubkey(), lamports);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Adds lamports to the reserve payroll threads are topped up from.
+    pub fn fund_fee_reserve(&self, authority: &Keypair, lamports: u64) -> Result<Signature> {
+        let ix = instruction::fund_fee_reserve(&self.program_id, &authority.pubkey(), lamports);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Takes lamports back out of the accounting's rent budget.
+    pub fn withdraw_rent_budget(&self, authority: &Keypair, lamports: u64) -> Result<Signature> {
+        let ix =
+            instruction::withdraw_rent_budget(&self.program_id, &authority.pubkey(), lamports);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Takes lamports back out of the fee reserve.
+    pub fn withdraw_fee_reserve(&self, authority: &Keypair, lamports: u64) -> Result<Signature> {
+        let ix =
+            instruction::withdraw_fee_reserve(&self.program_id, &authority.pubkey(), lamports);
+        self.send(&[ix], authority, &[])
+    }
+
+    pub fn set_thread_min_balance(&self, authority: &Keypair, lamports: u64) -> Result<Signature> {
+        let ix =
+            instruction::set_thread_min_balance(&self.program_id, &authority.pubkey(), lamports);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Sets whether moving one of the accounting's payrolls to a new
+    /// receiver needs `authority` to sign as well.
+    pub fn set_transfer_approval(&self, authority: &Keypair, required: bool) -> Result<Signature> {
+        let ix =
+            instruction::set_transfer_approval(&self.program_id, &authority.pubkey(), required);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Sets how many cycles' worth of pay receivers may have advanced at once.
+    pub fn set_advance_limit(&self, authority: &Keypair, max_cycles: u64) -> Result<Signature> {
+        let ix = instruction::set_advance_limit(&self.program_id, &authority.pubkey(), max_cycles);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Tops the threads of all active payrolls of `accounting` up to its
+    /// minimum thread balance, with `payer` paying the transaction fee.
+    /// Returns `None` if no payroll is active.
+    pub fn fund_threads(&self, payer: &Keypair, accounting: &Pubkey) -> Result<Option<Signature>> {
+        let payrolls: Vec<(Pubkey, Pubkey)> = self
+            .get_payrolls(accounting)?
+            .into_iter()
+            .filter(|(_, payroll)| payroll.active)
+            .map(|(address, payroll)| (address, payroll.thread))
+            .collect();
+        if payrolls.is_empty() {
+            return Ok(None);
+        }
+        let ix = instruction::fund_threads(&self.program_id, accounting, &payrolls);
+        self.send(&[ix], payer, &[]).map(Some)
+    }
+
+    /// Reconciles the recorded balance of the vault of `mint`, or of the
+    /// primary mint if `None`, with the tokens it really holds.
+    pub fn sync(
+        &self,
+        payer: &Keypair,
+        accounting: &Pubkey,
+        mint: Option<&Pubkey>,
+    ) -> Result<Signature> {
+        let (record, mint_vault) = self.get_mint_vault(accounting, mint)?;
+        let ix = instruction::sync(
+            &self.program_id,
+            accounting,
+            &mint_vault.mint,
+            &mint_vault.vault,
+            record.as_ref(),
+        );
+        self.send(&[ix], payer, &[])
+    }
+
+    /// Withdraws from the vault of `mint`, or of the primary mint if `None`.
+    pub fn withdraw(
+        &self,
+        authority: &Keypair,
+        mint: Option<&Pubkey>,
+        destination: &Pubkey,
+        args: &WithdrawArgs,
+    ) -> Result<Signature> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let (record, mint_vault) = self.get_mint_vault(&accounting, mint)?;
+        let ix = instruction::withdraw(
+            &self.program_id,
+            &authority.pubkey(),
+            &mint_vault.mint,
+            &mint_vault.token_program,
+            record.as_ref(),
+            destination,
+            args,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    pub fn update_payroll(
+        &self,
+        authority: &Keypair,
+        receiver: &Pubkey,
+        args: &UpdatePayrollArgs,
+    ) -> Result<Signature> {
+        let record = self.payroll_mint_vault(&authority.pubkey(), receiver)?;
+        let ix = instruction::update_payroll(
+            &self.program_id,
+            &authority.pubkey(),
+            receiver,
+            args,
+            record.as_ref(),
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Queues a one-off bonus or deduction on the next payment to `receiver`.
+    pub fn add_adjustment(
+        &self,
+        authority: &Keypair,
+        receiver: &Pubkey,
+        args: &AdjustmentArgs,
+    ) -> Result<Signature> {
+        let record = self.payroll_mint_vault(&authority.pubkey(), receiver)?;
+        let ix = instruction::add_adjustment(
+            &self.program_id,
+            &authority.pubkey(),
+            receiver,
+            args,
+            record.as_ref(),
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Resets the price the quote-denominated payroll paying `receiver`
+    /// measures slippage against to its feed's current price.
+    pub fn reprice_payroll(&self, authority: &Keypair, receiver: &Pubkey) -> Result<Signature> {
+        let payroll = self.get_payroll(&self.payroll_address(&authority.pubkey(), receiver))?;
+        let record = self.payroll_mint_vault(&authority.pubkey(), receiver)?;
+        let ix = instruction::reprice_payroll(
+            &self.program_id,
+            &authority.pubkey(),
+            receiver,
+            &payroll.pricing.price_feed,
+            record.as_ref(),
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    pub fn pause_payroll(&self, authority: &Keypair, receiver: &Pubkey) -> Result<Signature> {
+        let ix = instruction::pause_payroll(&self.program_id, &authority.pubkey(), receiver);
+        self.send(&[ix], authority, &[])
+    }
+
+    pub fn resume_payroll(&self, authority: &Keypair, receiver: &Pubkey) -> Result<Signature> {
+        let ix = instruction::resume_payroll(&self.program_id, &authority.pubkey(), receiver);
+        self.send(&[ix], authority, &[])
+    }
+
+    /// Cancels a payroll. With `allow_write_off`, an advance the receiver
+    /// hasn't repaid yet is written off instead of blocking it.
+    pub fn cancel_payroll(
+        &self,
+        authority: &Keypair,
+        receiver: &Pubkey,
+        allow_write_off: bool,
+    ) -> Result<Signature> {
+        let record = self.payroll_mint_vault(&authority.pubkey(), receiver)?;
+        let ix = instruction::cancel_payroll(
+            &self.program_id,
+            &authority.pubkey(),
+            receiver,
+            record.as_ref(),
+            allow_write_off,
+        );
+        self.send(&[ix], authority, &[])
+    }
+
+    /// The vault record of the mint a payroll pays in, or `None` for the
+    /// accounting's primary mint.
+    fn payroll_mint_vault(&self, authority: &Pubkey, receiver: &Pubkey) -> Result<Option<Pubkey>> {
+        let accounting = self.accounting_address(authority);
+        let payroll = self.get_payroll(&self.payroll_address(authority, receiver))?;
+        let (record, _) = self.get_mint_vault(&accounting, Some(&payroll.mint))?;
+        Ok(record)
+    }
+
+    /// Has the payroll `authority` pays `receiver` pay into `payout_account`,
+    /// another token account of the receiver's, from its next cycle.
+    pub fn set_payout_account(
+        &self,
+        receiver: &Keypair,
+        authority: &Pubkey,
+        payout_account: &Pubkey,
+    ) -> Result<Signature> {
+        let accounting = self.accounting_address(authority);
+        let record = self.payroll_mint_vault(authority, &receiver.pubkey())?;
+        let ix = instruction::set_payout_account(
+            &self.program_id,
+            &receiver.pubkey(),
+            &accounting,
+            payout_account,
+            record.as_ref(),
+        );
+        self.send(&[ix], receiver, &[])
+    }
+
+    /// Moves the payroll `authority` pays `receiver` to `new_receiver`.
+    /// `approver` is the authority's keypair, needed when the accounting
+    /// requires transfers to be approved.
+    pub fn transfer_payroll(
+        &self,
+        receiver: &Keypair,
+        authority: &Pubkey,
+        new_receiver: &Pubkey,
+        approver: Option<&Keypair>,
+    ) -> Result<Signature> {
+        let record = self.payroll_mint_vault(authority, &receiver.pubkey())?;
+        let ix = instruction::transfer_payroll(
+            &self.program_id,
+            &receiver.pubkey(),
+            new_receiver,
+            authority,
+            approver.is_some(),
+            record.as_ref(),
+        );
+        let signers: Vec<&Keypair> = approver.into_iter().collect();
+        self.send(&[ix], receiver, &signers)
+    }
+
+    /// Pays `receiver` `amount` of their payroll ahead of time, approved by
+    /// `authority`. The receiver pays the transaction fee.
+    pub fn request_advance(
+        &self,
+        receiver: &Keypair,
+        authority: &Keypair,
+        amount: u64,
+    ) -> Result<Signature> {
+        let accounting = self.accounting_address(&authority.pubkey());
+        let payroll =
+            self.get_payroll(&self.payroll_address(&authority.pubkey(), &receiver.pubkey()))?;
+        let (record, mint_vault) = self.get_mint_vault(&accounting, Some(&payroll.mint))?;
+        let payout_account = Some(payroll.payout_account).filter(|key| *key != Pubkey::default());
+        let ix = instruction::advance_request(
+            &self.program_id,
+            &receiver.pubkey(),
+            &authority.pubkey(),
+            amount,
+            &mint_vault.mint,
+            &mint_vault.vault,
+            &mint_vault.token_program,
+            record.as_ref(),
+            payout_account.as_ref(),
+        );
+        self.send(&[ix], receiver, &[authority])
+    }
+
+    pub fn close_history(&self, receiver: &Keypair, payroll: &Pubkey) -> Result<Signature> {
+        let ix = instruction::close_history(&self.program_id, &receiver.pubkey(), payroll);
+        self.send(&[ix], receiver, &[])
+    }
+
+    pub fn get_accounting(&self, address: &Pubkey) -> Result<Accounting> {
+        self.get_state(address)
+    }
+
+    pub fn get_payroll(&self, address: &Pubkey) -> Result<Payroll> {
+        self.get_state(address)
+    }
+
+    /// The vault holding `mint` for `accounting`, or its primary mint's if
+    /// `None`. The record address is `None` for the primary mint, whose vault
+    /// is kept in the accounting itself.
+    pub fn get_mint_vault(
+        &self,
+        accounting: &Pubkey,
+        mint: Option<&Pubkey>,
+    ) -> Result<(Option<Pubkey>, MintVault)> {
+        let accounting_info = self.get_accounting(accounting)?;
+        match mint {
+            Some(mint) if *mint != accounting_info.mint => {
+                let (record, _) = MintVault::find_address(&self.program_id, accounting, mint);
+                Ok((Some(record), self.get_state(&record)?))
+            }
+            _ => Ok((None, accounting_info.primary_vault(accounting))),
+        }
+    }
+
+    /// Cycles the vault of `mint`, or of the primary mint if `None`, can pay
+    /// every active payroll of `accounting` in that mint for. `u64::MAX`
+    /// means the balance outlasts them all.
+    pub fn get_runway(&self, accounting: &Pubkey, mint: Option<&Pubkey>) -> Result<u64> {
+        let (_, mint_vault) = self.get_mint_vault(accounting, mint)?;
+        let now = self.get_unix_timestamp()?;
+        let cycle_costs: Vec<(u64, Option<u64>)> = self
+            .get_payrolls(accounting)?
+            .into_iter()
+            .filter(|(_, payroll)| payroll.active && payroll.mint == mint_vault.mint)
+            .filter_map(|(_, payroll)| {
+                let cost = payroll.cycle_cost(mint_vault.decimals)?;
+                // Payrolls stop at their end date, whether or not they have
+                // a cycle limit.
+                let limit = payroll.remaining_cycles().unwrap_or(u64::MAX);
+                let cycles = payroll.cycles_before_end(now, limit);
+                Some((cost, Some(cycles).filter(|&cycles| cycles != u64::MAX)))
+            })
+            .collect();
+        Ok(state::runway(mint_vault.balance, &cycle_costs))
+    }
+
+    /// The cluster's current unix timestamp, read from the clock sysvar.
+    pub fn get_unix_timestamp(&self) -> Result<i64> {
+        let address = sysvar::clock::id();
+        let account = self
+            .rpc
+            .get_account(&address)?
+            .ok_or(ClientError::AccountNotFound(address))?;
+        let clock: Clock = from_account(&account).ok_or_else(|| {
+            ClientError::InvalidAccountData(address, io::ErrorKind::InvalidData.into())
+        })?;
+        Ok(clock.unix_timestamp)
+    }
+
+    /// Lamports left in the rent budget of `accounting`.
+    pub fn get_rent_budget(&self, accounting: &Pubkey) -> Result<u64> {
+        let (rent_budget, _) = Accounting::find_rent_budget_address(&self.program_id, accounting);
+        Ok(self
+            .rpc
+            .get_account(&rent_budget)?
+            .map_or(0, |account| account.lamports))
+    }
+
+    /// Vault records of all the additional mints of `accounting`.
+    pub fn get_mint_vaults(&self, accounting: &Pubkey) -> Result<Vec<(Pubkey, MintVault)>> {
+        // `MintVault.accounting` is the first field.
+        self.get_program_accounts(MintVault::SIZE, accounting)
+    }
+
+    /// Receipts recorded for a payroll, oldest first.
+    pub fn get_receipts(&self, payroll: &Pubkey) -> Result<Vec<Receipt>> {
+        let (history, _) = PaymentHistory::find_address(&self.program_id, payroll);
+        let data = self.get_account_data(&history)?;
+        PaymentHistory::unpack_receipts(&data)
+            .map_err(|e| ClientError::InvalidAccountData(history, e))
+    }
+
+    /// All payrolls belonging to `accounting`.
+    pub fn get_payrolls(&self, accounting: &Pubkey) -> Result<Vec<(Pubkey, Payroll)>> {
+        // `Payroll.accounting` is the first field.
+        self.get_program_accounts(Payroll::SIZE, accounting)
+    }
+
+    /// Program accounts of `size` bytes whose first field is `accounting`.
+    fn get_program_accounts<T: BorshDeserialize>(
+        &self,
+        size: usize,
+        accounting: &Pubkey,
+    ) -> Result<Vec<(Pubkey, T)>> {
+        let filters = vec![
+            RpcFilterType::DataSize(size as u64),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, accounting.as_ref())),
+        ];
+
+        self.rpc
+            .get_program_accounts(&self.program_id, filters)?
+            .into_iter()
+            .map(|(address, account)| {
+                T::try_from_slice(&account.data)
+                    .map(|state| (address, state))
+                    .map_err(|e| ClientError::InvalidAccountData(address, e))
+            })
+            .collect()
+    }
+
+    /// Events emitted by the program in a confirmed transaction.
+    pub fn get_events(&self, signature: &Signature) -> Result<Vec<ClockPayEvent>> {
+        let logs = self.rpc.get_transaction_logs(signature)?;
+        Ok(events::parse_logs(&self.program_id, &logs))
+    }
+
+    /// Signs and sends `instructions` with `payer` paying the fees.
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<Signature> {
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.rpc.send_and_confirm_transaction(&transaction)
+    }
+
+    /// The token program that owns `mint`, SPL Token or Token-2022.
+    pub fn mint_token_program(&self, mint: &Pubkey) -> Result<Pubkey> {
+        self.rpc
+            .get_account(mint)?
+            .map(|account| account.owner)
+            .ok_or(ClientError::AccountNotFound(*mint))
+    }
+
+    fn get_account_data(&self, address: &Pubkey) -> Result<Vec<u8>> {
+        self.rpc
+            .get_account(address)?
+            .map(|account| account.data)
+            .ok_or(ClientError::AccountNotFound(*address))
+    }
+
+    fn get_state<T: BorshDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let data = self.get_account_data(address)?;
+        T::try_from_slice(&data).map_err(|e| ClientError::InvalidAccountData(*address, e))
+    }
+}
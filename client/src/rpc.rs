@@ -0,0 +1,83 @@
+//! The cluster access `ClockPayClient` is built on.
+
+use crate::error::Result;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
+    rpc_filter::RpcFilterType,
+};
+use solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+
+/// What `ClockPayClient` reads from and sends to a cluster. `RpcClient`
+/// implements it against a live RPC node; tests can implement it over a
+/// `BanksClient` or any other stand-in.
+pub trait ClockPayRpc {
+    /// The account at `address`, or `None` if it doesn't exist.
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>>;
+
+    /// Accounts owned by `program_id` that pass every filter.
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>>;
+
+    fn get_latest_blockhash(&self) -> Result<Hash>;
+
+    /// Sends `transaction` and waits for it to be confirmed.
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature>;
+
+    /// Log messages of a confirmed transaction.
+    fn get_transaction_logs(&self, signature: &Signature) -> Result<Vec<String>>;
+}
+
+impl ClockPayRpc for RpcClient {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        Ok(self
+            .get_account_with_commitment(address, self.commitment())?
+            .value)
+    }
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        Ok(self.get_program_accounts_with_config(program_id, config)?)
+    }
+
+    fn get_latest_blockhash(&self) -> Result<Hash> {
+        Ok(RpcClient::get_latest_blockhash(self)?)
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        Ok(RpcClient::send_and_confirm_transaction(self, transaction)?)
+    }
+
+    fn get_transaction_logs(&self, signature: &Signature) -> Result<Vec<String>> {
+        let transaction = self.get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                commitment: Some(self.commitment()),
+                max_supported_transaction_version: Some(0),
+                ..RpcTransactionConfig::default()
+            },
+        )?;
+        Ok(transaction
+            .transaction
+            .meta
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+            .unwrap_or_default())
+    }
+}
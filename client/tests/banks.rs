@@ -0,0 +1,338 @@
+//! The client driven through a `BanksClient` stand-in for an RPC node, up to
+//! the point a payroll's thread would be created.
+
+use clockpay_client::{ClientError, ClockPayClient, ClockPayRpc, Result};
+use program::{
+    error::ClockPayError,
+    events::ClockPayEvent,
+    instruction::{NewPayrollArgs, WithdrawArgs},
+    oracle::PythOracle,
+    processor::Processor,
+    state::Payroll,
+};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_program_test::{processor, BanksClient, BanksClientError, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::InstructionError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+};
+use tokio::runtime::Runtime;
+
+const DEPOSIT: u64 = 1_000;
+
+/// Serves the client out of a local bank. The bank has no account index, so
+/// `get_program_accounts` searches every account a transaction has touched.
+struct BanksRpc {
+    runtime: Runtime,
+    banks_client: RefCell<BanksClient>,
+    addresses: RefCell<BTreeSet<Pubkey>>,
+    logs: RefCell<HashMap<Signature, Vec<String>>>,
+}
+
+impl BanksRpc {
+    fn start(program_id: Pubkey) -> (Self, Keypair) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let program_test = ProgramTest::new(
+            "program",
+            program_id,
+            processor!(Processor::process::<PythOracle>),
+        );
+        let (banks_client, payer, _) = runtime.block_on(program_test.start());
+        let rpc = Self {
+            runtime,
+            banks_client: RefCell::new(banks_client),
+            addresses: RefCell::default(),
+            logs: RefCell::default(),
+        };
+        (rpc, payer)
+    }
+}
+
+fn rpc_error(error: BanksClientError) -> ClientError {
+    ClientError::Rpc(TransportError::from(error).into())
+}
+
+impl ClockPayRpc for BanksRpc {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        let mut banks_client = self.banks_client.borrow_mut();
+        self.runtime
+            .block_on(banks_client.get_account(*address))
+            .map_err(rpc_error)
+    }
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let mut accounts = vec![];
+        for address in self.addresses.borrow().iter() {
+            let account = match self.get_account(address)? {
+                Some(account) if account.owner == *program_id => account,
+                _ => continue,
+            };
+            let matches = filters.iter().all(|filter| match filter {
+                RpcFilterType::DataSize(size) => account.data.len() as u64 == *size,
+                RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(&account.data),
+                _ => false,
+            });
+            if matches {
+                accounts.push((*address, account));
+            }
+        }
+        Ok(accounts)
+    }
+
+    fn get_latest_blockhash(&self) -> Result<Hash> {
+        let mut banks_client = self.banks_client.borrow_mut();
+        self.runtime
+            .block_on(banks_client.get_latest_blockhash())
+            .map_err(rpc_error)
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let mut banks_client = self.banks_client.borrow_mut();
+        let processed = self
+            .runtime
+            .block_on(banks_client.process_transaction_with_metadata(transaction.clone()))
+            .map_err(rpc_error)?;
+        processed
+            .result
+            .map_err(|e| ClientError::Rpc(TransportError::TransactionError(e).into()))?;
+
+        let signature = transaction.signatures[0];
+        self.addresses
+            .borrow_mut()
+            .extend(transaction.message.account_keys.iter().copied());
+        let logs = processed
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+        self.logs.borrow_mut().insert(signature, logs);
+        Ok(signature)
+    }
+
+    fn get_transaction_logs(&self, signature: &Signature) -> Result<Vec<String>> {
+        Ok(self
+            .logs
+            .borrow()
+            .get(signature)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+struct Env {
+    client: ClockPayClient<BanksRpc>,
+    authority: Keypair,
+    mint: Pubkey,
+    /// The authority's token account, which deposits come from and
+    /// withdrawals go to.
+    treasury: Pubkey,
+}
+
+impl Env {
+    /// An accounting for a fresh mint, with `DEPOSIT` tokens in the
+    /// authority's token account.
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let (rpc, authority) = BanksRpc::start(program_id);
+        let client = ClockPayClient::new(rpc, program_id);
+
+        let mint = Keypair::new();
+        let treasury = get_associated_token_address(&authority.pubkey(), &mint.pubkey());
+        let instructions = [
+            system_instruction::create_account(
+                &authority.pubkey(),
+                &mint.pubkey(),
+                1_000_000_000,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &authority.pubkey(),
+                None,
+                0,
+            )
+            .unwrap(),
+            create_associated_token_account(
+                &authority.pubkey(),
+                &authority.pubkey(),
+                &mint.pubkey(),
+                &spl_token::id(),
+            ),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &treasury,
+                &authority.pubkey(),
+                &[],
+                DEPOSIT,
+            )
+            .unwrap(),
+        ];
+        client.send(&instructions, &authority, &[&mint]).unwrap();
+        client
+            .create_accounting(&authority, &mint.pubkey())
+            .unwrap();
+
+        Env {
+            client,
+            authority,
+            mint: mint.pubkey(),
+            treasury,
+        }
+    }
+
+    fn accounting(&self) -> Pubkey {
+        self.client.accounting_address(&self.authority.pubkey())
+    }
+
+    fn create_payroll(&self, receiver: &Pubkey, amount: u64, cycles: u64) -> Result<Signature> {
+        let args = NewPayrollArgs {
+            amount,
+            cycles,
+            schedule: Payroll::pack_schedule("0 0 0 * * * *").unwrap(),
+            withholding: vec![],
+            decimals: None,
+            mint: None,
+            quote: None,
+            allow_overcommit: false,
+            start_ts: None,
+            end_ts: None,
+            prorate: false,
+        };
+        self.client
+            .create_payroll(&self.authority, receiver, &args, None, &[])
+    }
+}
+
+fn custom_error(error: ClientError) -> Option<u32> {
+    match error {
+        ClientError::Rpc(error) => match error.get_transaction_error()? {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(code),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn creates_and_funds_an_accounting() {
+    let env = Env::new();
+    let accounting = env.client.get_accounting(&env.accounting()).unwrap();
+    assert_eq!(accounting.authority, env.authority.pubkey());
+    assert_eq!(accounting.mint, env.mint);
+    assert_eq!(accounting.token_program, spl_token::id());
+    assert_eq!(accounting.balance, 0);
+
+    let signature = env
+        .client
+        .deposit(&env.authority, None, &env.treasury, DEPOSIT)
+        .unwrap();
+    assert_eq!(
+        env.client
+            .get_accounting(&env.accounting())
+            .unwrap()
+            .balance,
+        DEPOSIT
+    );
+    assert_eq!(
+        env.client.get_events(&signature).unwrap(),
+        vec![ClockPayEvent::Deposited {
+            accounting: env.accounting(),
+            amount: DEPOSIT,
+            balance: DEPOSIT,
+        }]
+    );
+}
+
+#[test]
+fn creates_and_lists_payrolls() {
+    let env = Env::new();
+    env.client
+        .deposit(&env.authority, None, &env.treasury, DEPOSIT)
+        .unwrap();
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    env.create_payroll(&first, 100, 5).unwrap();
+    env.create_payroll(&second, 50, 2).unwrap();
+
+    let payroll = env
+        .client
+        .get_payroll(&env.client.payroll_address(&env.authority.pubkey(), &first))
+        .unwrap();
+    assert_eq!(payroll.receiver, first);
+    assert_eq!(payroll.amount, 100);
+    assert_eq!(payroll.max_cycles, 5);
+    assert_eq!(payroll.committed, 500);
+
+    let mut receivers: Vec<Pubkey> = env
+        .client
+        .get_payrolls(&env.accounting())
+        .unwrap()
+        .into_iter()
+        .map(|(_, payroll)| payroll.receiver)
+        .collect();
+    receivers.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(receivers, expected);
+    assert_eq!(
+        env.client
+            .get_accounting(&env.accounting())
+            .unwrap()
+            .committed_amount,
+        600
+    );
+}
+
+#[test]
+fn reports_program_errors() {
+    let env = Env::new();
+    env.client
+        .deposit(&env.authority, None, &env.treasury, DEPOSIT)
+        .unwrap();
+    env.create_payroll(&Pubkey::new_unique(), 100, 5).unwrap();
+
+    let args = WithdrawArgs {
+        amount: DEPOSIT,
+        allow_overcommit: false,
+    };
+    let error = env
+        .client
+        .withdraw(&env.authority, None, &env.treasury, &args)
+        .unwrap_err();
+    assert_eq!(custom_error(error), Some(ClockPayError::Insolvent as u32));
+}
+
+#[test]
+fn reports_missing_accounts() {
+    let env = Env::new();
+    let payroll = env
+        .client
+        .payroll_address(&env.authority.pubkey(), &Pubkey::new_unique());
+    assert!(matches!(
+        env.client.get_payroll(&payroll),
+        Err(ClientError::AccountNotFound(address)) if address == payroll
+    ));
+}
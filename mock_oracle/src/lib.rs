@@ -0,0 +1,71 @@
+//! Test-only program that writes clockpay `PriceAccount` feeds, so quote
+//! denominated payrolls can be exercised on a local validator without a live
+//! oracle network. clockpay only trusts its feeds through
+//! `PriceAccountOracle`, which clockpay's entrypoint uses when built with the
+//! `mock-oracle` feature. Never deploy it to a public cluster: anyone can set
+//! any price on a feed it owns.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use program::oracle::PriceAccount;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+solana_program::declare_id!("FC5DyGP3eBUz47WyQQ4Htpz6q5jkdCGysqBN63xQ2TYp");
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Overwrites a feed with a new price.
+///
+/// Accounts expected:
+///
+/// 0. `[writable]` The feed, created beforehand with `PriceAccount::SIZE`
+///    bytes and owned by this program.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug)]
+pub struct SetPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    /// Publish time of the price. Zero stamps it with the cluster clock.
+    pub publish_time: i64,
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let args = SetPrice::try_from_slice(instruction_data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let account_info_iter = &mut accounts.iter();
+    let feed = next_account_info(account_info_iter)?;
+    if feed.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let publish_time = match args.publish_time {
+        0 => Clock::get()?.unix_timestamp,
+        publish_time => publish_time,
+    };
+    let price = PriceAccount {
+        magic: PriceAccount::MAGIC,
+        price: args.price,
+        conf: args.conf,
+        expo: args.expo,
+        publish_time,
+    };
+    price.serialize(&mut &mut feed.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+/// Creates a `SetPrice` instruction.
+pub fn set_price(program_id: &Pubkey, feed: &Pubkey, args: SetPrice) -> Instruction {
+    Instruction::new_with_borsh(*program_id, &args, vec![AccountMeta::new(*feed, false)])
+}
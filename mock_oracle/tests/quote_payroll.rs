@@ -0,0 +1,326 @@
+//! Quote-denominated payrolls priced by feeds the mock oracle writes, run
+//! against clockpay reading them with `PriceAccountOracle`.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use mock_oracle::SetPrice;
+use program::{
+    error::ClockPayError,
+    instruction::{self, NewPayrollArgs, QuoteArgs},
+    oracle::{Price, PriceAccount, PriceAccountOracle, PriceOracle, MOCK_ORACLE_PROGRAM_ID},
+    processor::Processor,
+    state::{Accounting, Payroll},
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    hash::Hash,
+    instruction::{Instruction, InstructionError},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+const MINT_DECIMALS: u8 = 6;
+
+struct Env {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    program_id: Pubkey,
+    mint: Pubkey,
+    feed: Pubkey,
+    /// A feed in the same format, but owned by another program.
+    untrusted_feed: Pubkey,
+}
+
+impl Env {
+    async fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let feed = Pubkey::new_unique();
+        let untrusted_feed = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "mock_oracle",
+            mock_oracle::id(),
+            processor!(mock_oracle::process_instruction),
+        );
+        program_test.add_program(
+            "program",
+            program_id,
+            processor!(Processor::process::<PriceAccountOracle>),
+        );
+        program_test.add_account(feed, feed_account(mock_oracle::id(), 0));
+        program_test.add_account(untrusted_feed, feed_account(Pubkey::new_unique(), 2_000));
+        let (banks_client, payer, recent_blockhash) = program_test.start().await;
+
+        let mut env = Env {
+            banks_client,
+            payer,
+            recent_blockhash,
+            program_id,
+            mint: Pubkey::default(),
+            feed,
+            untrusted_feed,
+        };
+        env.mint = env.create_mint().await;
+        let init_accounting = instruction::init_accounting(
+            &env.program_id,
+            &env.payer.pubkey(),
+            &env.mint,
+            &spl_token::id(),
+        );
+        env.send(&[init_accounting], &[]).await.unwrap();
+        env
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.recent_blockhash,
+        );
+        self.banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    async fn create_mint(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self.banks_client.get_rent().await.unwrap();
+        let instructions = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &self.payer.pubkey(),
+                None,
+                MINT_DECIMALS,
+            )
+            .unwrap(),
+        ];
+        self.send(&instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    /// Publishes `price * 10^-3` quote units per token, stamped with
+    /// `publish_time` or the cluster clock if zero.
+    async fn set_price(&mut self, price: i64, publish_time: i64) {
+        let set_price = mock_oracle::set_price(
+            &mock_oracle::id(),
+            &self.feed,
+            SetPrice {
+                price,
+                conf: 0,
+                expo: -3,
+                publish_time,
+            },
+        );
+        self.send(&[set_price], &[]).await.unwrap();
+    }
+
+    /// Creates a payroll of 50.00 quote units a cycle priced by `feed`.
+    async fn new_quote_payroll(
+        &mut self,
+        receiver: &Pubkey,
+        feed: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let args = NewPayrollArgs {
+            amount: 5_000,
+            cycles: 12,
+            schedule: Payroll::pack_schedule("0 0 9 1 * * *").unwrap(),
+            withholding: vec![],
+            decimals: None,
+            mint: None,
+            quote: Some(QuoteArgs {
+                quote_decimals: 2,
+                max_price_age: 60,
+                max_confidence_bps: 100,
+                max_slippage_bps: 500,
+            }),
+            allow_overcommit: true,
+            start_ts: None,
+            end_ts: None,
+            prorate: false,
+        };
+        let new_payroll = instruction::new_payroll(
+            &self.program_id,
+            &self.payer.pubkey(),
+            receiver,
+            &args,
+            None,
+            Some(feed),
+            &[],
+        );
+        self.send(&[new_payroll], &[]).await
+    }
+
+    async fn reprice(&mut self, receiver: &Pubkey) -> Result<(), TransactionError> {
+        let reprice = instruction::reprice_payroll(
+            &self.program_id,
+            &self.payer.pubkey(),
+            receiver,
+            &self.feed,
+            None,
+        );
+        self.send(&[reprice], &[]).await
+    }
+
+    async fn payroll(&mut self, receiver: &Pubkey) -> Payroll {
+        let (accounting, _) = Accounting::find_address(&self.program_id, &self.payer.pubkey());
+        let (payroll, _) = Payroll::find_address(&self.program_id, &accounting, receiver);
+        let account = self
+            .banks_client
+            .get_account(payroll)
+            .await
+            .unwrap()
+            .unwrap();
+        Payroll::try_from_slice(&account.data).unwrap()
+    }
+}
+
+fn feed_account(owner: Pubkey, price: i64) -> Account {
+    let mut data = vec![0; PriceAccount::SIZE];
+    if price > 0 {
+        let feed = PriceAccount {
+            magic: PriceAccount::MAGIC,
+            price,
+            conf: 0,
+            expo: -3,
+            publish_time: 0,
+        };
+        feed.serialize(&mut &mut data[..]).unwrap();
+    }
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner,
+        ..Account::default()
+    }
+}
+
+fn read_price(key: &Pubkey, mut account: Account) -> Result<Price, ProgramError> {
+    let feed = AccountInfo::new(
+        key,
+        false,
+        false,
+        &mut account.lamports,
+        &mut account.data,
+        &account.owner,
+        false,
+        0,
+    );
+    PriceAccountOracle::read_price(&feed)
+}
+
+fn custom_error(error: ClockPayError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn mock_oracle_writes_feeds_clockpay_trusts() {
+    assert_eq!(MOCK_ORACLE_PROGRAM_ID, mock_oracle::id());
+
+    let mut env = Env::new().await;
+    env.set_price(2_000, 1_700_000_000).await;
+
+    let feed = env
+        .banks_client
+        .get_account(env.feed)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        read_price(&env.feed, feed.clone()).unwrap(),
+        Price {
+            price: 2_000,
+            conf: 0,
+            expo: -3,
+            publish_time: 1_700_000_000,
+        }
+    );
+
+    let untrusted = Account {
+        owner: Pubkey::new_unique(),
+        ..feed
+    };
+    assert_eq!(
+        read_price(&env.feed, untrusted).unwrap_err(),
+        ProgramError::from(ClockPayError::InvalidPrice)
+    );
+}
+
+#[tokio::test]
+async fn new_payroll_records_the_feed_price() {
+    let mut env = Env::new().await;
+    env.set_price(2_000, 0).await;
+    let receiver = Pubkey::new_unique();
+    let feed = env.feed;
+    env.new_quote_payroll(&receiver, &feed).await.unwrap();
+
+    let payroll = env.payroll(&receiver).await;
+    assert_eq!(payroll.pricing.price_feed, env.feed);
+    assert_eq!(payroll.pricing.last_price, 2_000);
+    assert_eq!(payroll.pricing.last_expo, -3);
+    // 50.00 quote units at 2.000 a token, for 12 cycles.
+    assert_eq!(payroll.committed, 12 * 25 * 10u64.pow(MINT_DECIMALS as u32));
+}
+
+#[tokio::test]
+async fn new_payroll_refuses_feeds_of_other_programs() {
+    let mut env = Env::new().await;
+    let feed = env.untrusted_feed;
+    let error = env
+        .new_quote_payroll(&Pubkey::new_unique(), &feed)
+        .await
+        .unwrap_err();
+    assert_eq!(error, custom_error(ClockPayError::InvalidPrice));
+}
+
+#[tokio::test]
+async fn reprice_resets_the_price_slippage_is_measured_against() {
+    let mut env = Env::new().await;
+    env.set_price(2_000, 0).await;
+    let receiver = Pubkey::new_unique();
+    let feed = env.feed;
+    env.new_quote_payroll(&receiver, &feed).await.unwrap();
+
+    // A 50% move, well past the payroll's 5% slippage.
+    env.set_price(3_000, 0).await;
+    env.reprice(&receiver).await.unwrap();
+
+    let payroll = env.payroll(&receiver).await;
+    assert_eq!(payroll.pricing.last_price, 3_000);
+    assert_eq!(payroll.pricing.last_expo, -3);
+}
+
+#[tokio::test]
+async fn reprice_refuses_a_stale_price() {
+    let mut env = Env::new().await;
+    env.set_price(2_000, 0).await;
+    let receiver = Pubkey::new_unique();
+    let feed = env.feed;
+    env.new_quote_payroll(&receiver, &feed).await.unwrap();
+
+    env.set_price(3_000, 1).await;
+    let error = env.reprice(&receiver).await.unwrap_err();
+    assert_eq!(error, custom_error(ClockPayError::StalePrice));
+    assert_eq!(env.payroll(&receiver).await.pricing.last_price, 2_000);
+}
@@ -4,11 +4,19 @@ use solana_program::{
 
 use crate::processor;
 
+/// Oracle quote payrolls are priced with. Builds with the `mock-oracle`
+/// feature trust feeds written by the `mock_oracle` test program instead of
+/// Pyth. Never deploy them to a public cluster.
+#[cfg(not(feature = "mock-oracle"))]
+type Oracle = crate::oracle::PythOracle;
+#[cfg(feature = "mock-oracle")]
+type Oracle = crate::oracle::PriceAccountOracle;
+
 entrypoint!(process_instruction);
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    processor::Processor::process(program_id, accounts, instruction_data)
+    processor::Processor::process::<Oracle>(program_id, accounts, instruction_data)
 }
@@ -20,6 +20,56 @@ pub enum ClockPayError {
     ScheduleLengthExceeded,
     #[error("Receiver's payment cycles completed")]
     TriedExceedingPaymentLimit,
+    #[error("Too many deductions for one payroll")]
+    TooManyDeductions,
+    #[error("Invalid deduction")]
+    InvalidDeduction,
+    #[error("Deductions exceed the payment amount")]
+    DeductionsExceedAmount,
+    #[error("Payroll still has payments outstanding")]
+    PaymentsOutstanding,
+    #[error("Not enough funds in the vault")]
+    InsufficientFunds,
+    #[error("Not supported in this accounting's funding mode")]
+    WrongFundingMode,
+    #[error("Payment exceeds the treasury's delegated allowance")]
+    AllowanceExceeded,
+    #[error("Mint has an extension payrolls cannot support")]
+    UnsupportedMint,
+    #[error("Amount decimals don't match the mint's")]
+    WrongDecimals,
+    #[error("Accounting has no vault for this mint")]
+    UnknownMint,
+    #[error("Price feed account is invalid")]
+    InvalidPrice,
+    #[error("Price is older than the payroll allows")]
+    StalePrice,
+    #[error("Price confidence interval is wider than the payroll allows")]
+    UncertainPrice,
+    #[error("Price moved more than the payroll's maximum slippage")]
+    PriceSlippageExceeded,
+    #[error("Arithmetic overflow or underflow")]
+    ArithmeticError,
+    #[error("Payroll schedule is not valid UTF-8")]
+    InvalidSchedule,
+    #[error("Vault balance doesn't cover what its payrolls are committed to")]
+    Insolvent,
+    #[error("Payroll must end after it starts, and in the future")]
+    InvalidPeriod,
+    #[error("Payroll's end date has passed")]
+    PayrollEnded,
+    #[error("Native SOL payrolls are paid to the receiver's wallet")]
+    NativePayout,
+    #[error("Payroll already pays this receiver")]
+    SameReceiver,
+    #[error("Advance exceeds what the accounting allows")]
+    AdvanceLimitExceeded,
+    #[error("Too many adjustments queued on one payroll")]
+    TooManyAdjustments,
+    #[error("Payroll must pay at least one cycle")]
+    InvalidCycles,
+    #[error("Payroll has an advance outstanding")]
+    AdvanceOutstanding,
 }
 
 impl From<ClockPayError> for ProgramError {
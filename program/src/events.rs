@@ -0,0 +1,354 @@
+use crate::state::{Adjustment, Payroll};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+/// Version byte prefixed to every event. Bumped whenever an existing
+/// event's layout changes; new events are only ever appended.
+pub const EVENT_VERSION: u8 = 2;
+
+/// Structured events logged with `sol_log_data`, one per state transition.
+///
+/// Each event is logged as a single `Program data:` entry holding
+/// `EVENT_VERSION` followed by the borsh-serialized event.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ClockPayEvent {
+    AccountingInitialized {
+        accounting: Pubkey,
+        authority: Pubkey,
+        mint: Pubkey,
+        vault: Pubkey,
+    },
+    /// `balance` is that of the deposited mint's vault.
+    Deposited {
+        accounting: Pubkey,
+        amount: u64,
+        balance: u64,
+    },
+    PayrollCreated {
+        accounting: Pubkey,
+        payroll: Pubkey,
+        receiver: Pubkey,
+        amount: u64,
+        max_cycles: u64,
+    },
+    PaymentStarted {
+        payroll: Pubkey,
+        thread: Pubkey,
+        receiver_wallet: Pubkey,
+    },
+    /// `adjustment` is what the payroll's queued adjustments added to, or
+    /// took off, the pay left after deductions.
+    Paid {
+        payroll: Pubkey,
+        receiver_wallet: Pubkey,
+        thread: Pubkey,
+        cycle: u64,
+        gross_amount: u64,
+        net_amount: u64,
+        withheld: [u64; Payroll::MAX_DEDUCTIONS],
+        timestamp: i64,
+        adjustment: i64,
+    },
+    PaymentFailed {
+        payroll: Pubkey,
+        error: u64,
+    },
+    HistoryClosed {
+        payroll: Pubkey,
+        receiver: Pubkey,
+    },
+    /// `balance` is that of the withdrawn mint's vault.
+    Withdrawn {
+        accounting: Pubkey,
+        amount: u64,
+        balance: u64,
+    },
+    PayrollUpdated {
+        payroll: Pubkey,
+        amount: u64,
+        max_cycles: u64,
+    },
+    PayrollPaused {
+        payroll: Pubkey,
+    },
+    PayrollResumed {
+        payroll: Pubkey,
+    },
+    PayrollCancelled {
+        payroll: Pubkey,
+        cycles_completed: u64,
+        total_amount_disbursed: u64,
+    },
+    AllowanceSet {
+        accounting: Pubkey,
+        treasury: Pubkey,
+        allowance: u64,
+    },
+    /// Token-2022 transfer fees withheld from a `Pay`'s transfers. The
+    /// amounts in the matching `Paid` event are before these fees.
+    TransferFeesCharged {
+        payroll: Pubkey,
+        cycle: u64,
+        fees: u64,
+    },
+    MintAdded {
+        accounting: Pubkey,
+        mint: Pubkey,
+        vault: Pubkey,
+    },
+    /// The price a quote-denominated payroll's `quote_amount` was converted
+    /// at for the matching `Paid` event's `gross_amount`.
+    QuoteConverted {
+        payroll: Pubkey,
+        cycle: u64,
+        quote_amount: u64,
+        price: u64,
+        expo: i32,
+    },
+    /// `balance` is the rent budget's lamports after the deposit.
+    RentBudgetFunded {
+        accounting: Pubkey,
+        amount: u64,
+        balance: u64,
+    },
+    /// `Pay` created the receiver's associated token account out of the
+    /// accounting's rent budget.
+    ReceiverAccountCreated {
+        payroll: Pubkey,
+        receiver_wallet: Pubkey,
+    },
+    /// `reserve` is the accounting's fee reserve after the deposit.
+    FeeReserveFunded {
+        accounting: Pubkey,
+        amount: u64,
+        reserve: u64,
+    },
+    ThreadMinBalanceSet {
+        accounting: Pubkey,
+        lamports: u64,
+    },
+    /// `amount` lamports moved from the fee reserve into a payroll's thread.
+    ThreadToppedUp {
+        payroll: Pubkey,
+        thread: Pubkey,
+        amount: u64,
+        reserve: u64,
+    },
+    /// The fee reserve can no longer top every active thread up to the
+    /// minimum balance, `required` lamports in all.
+    FeeReserveLow {
+        accounting: Pubkey,
+        reserve: u64,
+        required: u64,
+    },
+    /// A vault's recorded balance was reconciled with its token account.
+    BalanceSynced {
+        accounting: Pubkey,
+        mint: Pubkey,
+        previous: u64,
+        balance: u64,
+    },
+    /// A vault's balance fell below what its payrolls still owe.
+    Overcommitted {
+        accounting: Pubkey,
+        mint: Pubkey,
+        balance: u64,
+        committed: u64,
+    },
+    /// A cycle was paid for `worked` seconds out of its `period`.
+    CycleProrated {
+        payroll: Pubkey,
+        cycle: u64,
+        worked: i64,
+        period: i64,
+        gross_amount: u64,
+    },
+    /// The receiver pointed the payroll at another token account of theirs.
+    PayoutAccountSet {
+        payroll: Pubkey,
+        payout_account: Pubkey,
+    },
+    TransferApprovalSet {
+        accounting: Pubkey,
+        required: bool,
+    },
+    /// The receiver moved their payroll, now at `new_payroll`, to
+    /// `new_receiver`.
+    PayrollTransferred {
+        payroll: Pubkey,
+        new_payroll: Pubkey,
+        new_receiver: Pubkey,
+    },
+    AdvanceLimitSet {
+        accounting: Pubkey,
+        max_cycles: u64,
+    },
+    /// `amount` was paid ahead of the payroll's next cycles. `outstanding`
+    /// is what they now have to repay.
+    AdvancePaid {
+        payroll: Pubkey,
+        receiver_wallet: Pubkey,
+        amount: u64,
+        outstanding: u64,
+    },
+    /// `amount` of a cycle's net pay was kept back to repay an advance.
+    AdvanceRepaid {
+        payroll: Pubkey,
+        cycle: u64,
+        amount: u64,
+        outstanding: u64,
+    },
+    AdjustmentAdded {
+        payroll: Pubkey,
+        amount: i64,
+        memo: [u8; Adjustment::MEMO_LEN],
+    },
+    /// The authority reset the price later payments measure slippage
+    /// against to `price * 10^expo`.
+    PayrollRepriced {
+        payroll: Pubkey,
+        price: u64,
+        expo: i32,
+    },
+    /// The payroll paid its last cycle, or its end date passed, and it was
+    /// marked inactive with its commitment released. Its thread stays until
+    /// the payroll is cancelled.
+    PayrollSettled {
+        payroll: Pubkey,
+        cycles_completed: u64,
+        total_amount_disbursed: u64,
+    },
+    FeeReserveWithdrawn {
+        accounting: Pubkey,
+        amount: u64,
+        reserve: u64,
+    },
+    RentBudgetWithdrawn {
+        accounting: Pubkey,
+        amount: u64,
+        balance: u64,
+    },
+    /// A payroll was cancelled before its advance was repaid, forgoing
+    /// `amount`.
+    AdvanceWrittenOff {
+        payroll: Pubkey,
+        amount: u64,
+    },
+}
+
+impl ClockPayEvent {
+    pub fn emit(&self) {
+        let mut data = vec![EVENT_VERSION];
+        // Serializing into a Vec cannot fail.
+        self.serialize(&mut data).unwrap();
+        sol_log_data(&[&data]);
+    }
+
+    /// Decodes a single `sol_log_data` entry. Returns `None` for data that
+    /// isn't a clockpay event of a known version.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (version, event) = data.split_first()?;
+        if *version != EVENT_VERSION {
+            return None;
+        }
+        Self::try_from_slice(event).ok()
+    }
+}
+
+/// Extracts the events emitted by `program_id` from a transaction's log
+/// messages, skipping data logged by any other program in the transaction.
+///
+/// Invocations are tracked from the runtime's `<program> invoke [n]`,
+/// `<program> success` and `<program> failed: ...` lines; what programs log
+/// themselves never opens or closes one.
+pub fn parse_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<ClockPayEvent> {
+    let program_id = program_id.to_string();
+    let mut invocations: Vec<&str> = vec![];
+    let mut events = vec![];
+
+    for line in logs.iter().map(AsRef::as_ref) {
+        let line = match line.strip_prefix("Program ") {
+            Some(line) => line,
+            None => continue,
+        };
+        if line.starts_with("log: ") {
+            continue;
+        }
+
+        if let Some(data) = line.strip_prefix("data: ") {
+            if invocations.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            events.extend(
+                data.split_whitespace()
+                    .filter_map(|field| base64::decode(field).ok())
+                    .filter_map(|bytes| ClockPayEvent::decode(&bytes)),
+            );
+        } else if let Some((program, status)) = line.split_once(' ') {
+            if status.starts_with("invoke [") {
+                invocations.push(program);
+            } else if (status == "success" || status.starts_with("failed: "))
+                && invocations.last() == Some(&program)
+            {
+                invocations.pop();
+            }
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_line(event: &ClockPayEvent) -> String {
+        let mut data = vec![EVENT_VERSION];
+        event.serialize(&mut data).unwrap();
+        format!("Program data: {}", base64::encode(data))
+    }
+
+    #[test]
+    fn parse_logs_ignores_programs_logging_failed() {
+        let program_id = Pubkey::new_unique();
+        let token_program = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let paused = ClockPayEvent::PayrollPaused {
+            payroll: Pubkey::new_unique(),
+        };
+        let resumed = ClockPayEvent::PayrollResumed {
+            payroll: Pubkey::new_unique(),
+        };
+
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Transfer vault to receiver".to_string(),
+            format!("Program {} invoke [2]", token_program),
+            "Program log: Instruction: TransferChecked".to_string(),
+            "Program log: first attempt failed, retrying".to_string(),
+            format!("Program log: {} success", token_program),
+            format!(
+                "Program {} consumed 4645 of 180000 compute units",
+                token_program
+            ),
+            format!("Program {} success", token_program),
+            data_line(&paused),
+            format!(
+                "Program {} consumed 24645 of 200000 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", other_program),
+            data_line(&resumed),
+            format!(
+                "Program {} failed: custom program error: 0x1",
+                other_program
+            ),
+            format!("Program {} invoke [1]", program_id),
+            data_line(&resumed),
+            format!("Program {} success", program_id),
+        ];
+
+        assert_eq!(parse_logs(&program_id, &logs), vec![paused, resumed]);
+    }
+}
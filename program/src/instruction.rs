@@ -1,6 +1,16 @@
 use crate::error::ClockPayError::InvalidInstruction;
-use borsh::BorshDeserialize;
-use solana_program::program_error::ProgramError;
+use crate::state::{Accounting, Adjustment, DeductionKind, MintVault, PaymentHistory, Payroll};
+use crate::token;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program::ID as SystemProgramId,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, ID as AssociatedTokenProgramId,
+};
 
 pub enum ClockPayInstruction {
     /// Initializes an accounting instance. Represents the user's global state.
@@ -13,7 +23,7 @@ pub enum ClockPayInstruction {
     /// 2. `[writable]` The program account that stores the state. A pda with seeds &[b"accounting".as_ref(), initializer.key.as_ref()]
     /// 3. `[writable]` The vault: Associated token account for the state pda
     /// 4. `[]` The System Program
-    /// 5. `[]` The Token Program
+    /// 5. `[]` The Token Program that owns the mint: SPL Token or Token-2022
     /// 6. `[]` The AToken Program
     InitAccounting,
     /// Deposits into the Accounting vault
@@ -26,6 +36,12 @@ pub enum ClockPayInstruction {
     /// 2. `[writable]` The authority's token account to be debited.
     /// 3. `[writable]` The vault
     /// 4. `[]` The Token Program,
+    /// 5. `[]` The token mint
+    /// 6. `[writable]` The mint's vault record, unless it is the accounting's primary mint
+    /// 7. `[]` The System Program, only when depositing lamports
+    ///
+    /// For the native mint, passing the authority itself as account 2
+    /// deposits `amount` lamports, which are wrapped into the vault.
     Deposit(DepositArgs),
     /// Initializes a new payroll
     ///
@@ -37,6 +53,13 @@ pub enum ClockPayInstruction {
     /// 3. `[writable]` The payroll account to be created. A pda with seeds [b"payroll".as_ref(), accounting.key.as_ref(), receiver.key.as_ref()]
     /// 4. `[]` The receiver's key
     /// 5. `[]` The System Program,
+    /// 6. `[writable]` The vault record of `NewPayrollArgs::mint`, unless it is the accounting's primary mint
+    /// 7. `[]` The price feed, only if `NewPayrollArgs::quote` is set
+    /// 8. `[]` One destination token account per entry in `NewPayrollArgs::withholding`, in order.
+    ///
+    /// Fails with `Insolvent` if the vault's balance doesn't cover what its
+    /// payrolls are committed to once this one is added, unless
+    /// `NewPayrollArgs::allow_overcommit` is set.
     NewPayroll(NewPayrollArgs),
     /// Initializes the clockwork instance to pay iteratively
     ///
@@ -47,34 +70,480 @@ pub enum ClockPayInstruction {
     /// 2. `[writable]` The accounting state pda. Acts as a signer
     /// 3. `[writable]` The payroll state pda
     /// 4. `[writable]` The vault
-    /// 5. `[writable]` The receiver's wallet
+    /// 5. `[writable]` The receiver's associated token account for the payroll mint, which need not
+    ///    exist yet, or the payout account set with `SetPayoutAccount`.
+    ///    For the native mint, the pda wrapped SOL is unwrapped through, as in `Pay`
     /// 6. `[writable]` The Thread
     /// 7. `[]` The Thread program
     /// 8. `[]` The Token Program
     /// 9. `[]` The System Program
+    /// 10. `[writable]` The payment history account to be created. A pda with seeds [b"history".as_ref(), payroll.key.as_ref()]
+    /// 11. `[]` The payroll mint's vault record, unless it is the accounting's primary mint
     InitPayment,
     /// Automated by clockwork from inside the make payment instruction
     ///
+    /// The payroll's last cycle, or its last before `end_ts`, settles it: it
+    /// is marked inactive and its commitment released.
+    ///
     /// Accounts expected:
     ///
     /// 1. `[writable]` The payroll state.
     /// 2. `[writable]` The accounting state.
     /// 3. `[writable]` The vault to be withdrawn from
-    /// 4. `[writable]` The receiver's wallet
-    /// 5. `[]` The token program
+    /// 4. `[writable]` The receiver's associated token account for the payroll mint, created if missing,
+    ///    or the payout account set with `SetPayoutAccount`.
+    ///    For the native mint, the temporary account wrapped SOL is unwrapped through instead. A pda with seeds [b"unwrap".as_ref(), payroll.key.as_ref()]
+    /// 5. `[signer]` The payroll's thread
+    /// 6. `[]` The token program
+    /// 7. `[writable]` The payment history account
+    /// 8. `[]` The payroll's token mint
+    /// 9. `[writable]` The receiver, credited directly with native SOL payments
+    /// 10. `[writable]` The accounting's rent budget. A pda with seeds [b"rent_budget".as_ref(), accounting.key.as_ref()]
+    /// 11. `[]` The System Program
+    /// 12. `[]` The AToken Program
+    /// 13. `[writable]` The mint's vault record, unless it is the accounting's primary mint
+    /// 14. `[]` The payroll's price feed, only for quote-denominated payrolls
+    /// 15. `[writable]` One destination token account per payroll deduction, in order.
     Pay,
+    /// Closes a payroll's payment history once it has no payments outstanding,
+    /// refunding the rent to the receiver.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer, writable]` The receiver.
+    /// 2. `[]` The payroll state.
+    /// 3. `[writable]` The payment history account.
+    CloseHistory,
+    /// Withdraws from the Accounting vault
+    ///
+    /// Fails with `Insolvent` if what is left doesn't cover what the vault's
+    /// payrolls are committed to, unless `WithdrawArgs::allow_overcommit`
+    /// is set.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer]` The authority of the Accounting state account.
+    /// 2. `[writable]` The Accounting state account.
+    /// 3. `[writable]` The vault
+    /// 4. `[writable]` The token account to be credited.
+    /// 5. `[]` The Token Program
+    /// 6. `[]` The token mint
+    /// 7. `[writable]` The mint's vault record, unless it is the accounting's primary mint
+    Withdraw(WithdrawArgs),
+    /// Changes the amount or number of cycles of an existing payroll
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer]` The authority of the Accounting state account.
+    /// 2. `[writable]` The Accounting state account.
+    /// 3. `[writable]` The payroll state.
+    /// 4. `[writable]` The payroll mint's vault record, unless it is the accounting's primary mint
+    UpdatePayroll(UpdatePayrollArgs),
+    /// Pauses a started payroll's thread
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer]` The authority of the Accounting state account.
+    /// 2. `[writable]` The Accounting state account.
+    /// 3. `[writable]` The payroll state.
+    /// 4. `[writable]` The Thread
+    /// 5. `[]` The Thread program
+    PausePayroll,
+    /// Resumes a paused payroll's thread
+    ///
+    ///
+    /// Accounts expected: same as `PausePayroll`.
+    ResumePayroll,
+    /// Deletes a payroll's thread, returning its lamports to the fee reserve,
+    /// and closes the payroll, refunding rent to the authority. The payment
+    /// history is kept for the receiver. Refused while an advance is
+    /// outstanding, unless `allow_write_off` is set.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer, writable]` The authority of the Accounting state account.
+    /// 2. `[writable]` The Accounting state account.
+    /// 3. `[writable]` The payroll state.
+    /// 4. `[writable]` The Thread
+    /// 5. `[]` The Thread program
+    /// 6. `[writable]` The payroll mint's vault record, unless it is the accounting's primary mint
+    CancelPayroll(CancelPayrollArgs),
+    /// Initializes an accounting instance that pays from the initializer's own
+    /// treasury token account instead of a custodial vault. The accounting pda
+    /// is approved as a delegate for `allowance` tokens.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The initializer.
+    /// 1. `[]` Token mint
+    /// 2. `[writable]` The program account that stores the state. A pda with seeds &[b"accounting".as_ref(), initializer.key.as_ref()]
+    /// 3. `[writable]` The initializer's treasury token account
+    /// 4. `[]` The System Program
+    /// 5. `[]` The Token Program that owns the mint: SPL Token or Token-2022
+    InitDelegatedAccounting(AllowanceArgs),
+    /// Re-approves the accounting pda as a delegate on the treasury, replacing
+    /// the remaining allowance
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The authority of the Accounting state account.
+    /// 1. `[writable]` The Accounting state account.
+    /// 2. `[writable]` The treasury token account
+    /// 3. `[]` The Token Program
+    SetAllowance(AllowanceArgs),
+    /// Adds a vault for another mint to a custodial accounting, so payrolls
+    /// can pay in it besides the primary mint
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The authority of the Accounting state account.
+    /// 1. `[]` The Accounting state account.
+    /// 2. `[]` Token mint
+    /// 3. `[writable]` The mint's vault record. A pda with seeds &[b"vault".as_ref(), accounting.key.as_ref(), mint.key.as_ref()]
+    /// 4. `[writable]` The vault: Associated token account of the accounting pda for the mint
+    /// 5. `[]` The System Program
+    /// 6. `[]` The Token Program that owns the mint: SPL Token or Token-2022
+    /// 7. `[]` The AToken Program
+    AddMint,
+    /// Transfers lamports from the authority into the accounting's rent
+    /// budget, which pays for receivers' token accounts created by `Pay`
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The authority of the Accounting state account.
+    /// 1. `[]` The Accounting state account.
+    /// 2. `[writable]` The rent budget. A pda with seeds [b"rent_budget".as_ref(), accounting.key.as_ref()]
+    /// 3. `[]` The System Program
+    FundRentBudget(FundRentBudgetArgs),
+    /// Transfers lamports from the authority into the accounting's fee
+    /// reserve, which tops up payroll threads
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The authority of the Accounting state account.
+    /// 1. `[writable]` The Accounting state account.
+    /// 2. `[]` The System Program
+    FundFeeReserve(FundFeeReserveArgs),
+    /// Sets the balance payroll threads are topped up to from the fee reserve
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The authority of the Accounting state account.
+    /// 1. `[writable]` The Accounting state account.
+    SetThreadMinBalance(ThreadMinBalanceArgs),
+    /// Tops payroll threads up to the accounting's minimum balance out of its
+    /// fee reserve. Anyone may call it, e.g. to revive a thread that ran dry.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable]` The Accounting state account.
+    /// 1. `[]` A payroll of the accounting, followed by
+    /// 2. `[writable]` its Thread, repeated for every payroll to top up.
+    FundThreads,
+    /// Sets a vault's recorded balance to what its token account really
+    /// holds, e.g. after tokens were sent to it directly. Anyone may call it.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable]` The Accounting state account.
+    /// 1. `[]` The token mint
+    /// 2. `[]` The vault
+    /// 3. `[writable]` The mint's vault record, unless it is the accounting's primary mint
+    Sync,
+    /// Creates several payrolls at once, and starts them if
+    /// `NewPayrollBatchArgs::start` is set. Fails as a whole if any of them
+    /// would fail on its own.
+    ///
+    ///
+    /// Accounts expected, for every entry of `NewPayrollBatchArgs::payrolls` in order:
+    ///
+    /// 1. The accounts of its `NewPayroll` instruction.
+    /// 2. If `NewPayrollBatchArgs::start` is set, the accounts of its `InitPayment` instruction.
+    NewPayrollBatch(NewPayrollBatchArgs),
+    /// Creates a payroll and starts its thread in one step, so a failure
+    /// leaves nothing behind. `NewPayroll` then `InitPayment` still work for
+    /// payrolls started later.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. The accounts of `NewPayroll`.
+    /// 2. The accounts of `InitPayment`.
+    CreateAndStartPayroll(NewPayrollArgs),
+    /// Points a payroll's payments at another token account of the
+    /// receiver's, updating its thread if it has been started. Not available
+    /// for native SOL payrolls.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer]` The receiver
+    /// 2. `[writable]` The payroll state
+    /// 3. `[]` The Accounting state account.
+    /// 4. `[]` The receiver's token account to pay, for the payroll mint
+    /// 5. `[writable]` The Thread
+    /// 6. `[]` The Thread program
+    /// 7. `[]` The System Program
+    /// 8. `[]` The payroll mint's vault record, unless it is the accounting's primary mint
+    SetPayoutAccount,
+    /// Sets whether `TransferPayroll` needs the authority's signature.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The authority of the Accounting state account.
+    /// 1. `[writable]` The Accounting state account.
+    SetTransferApproval(TransferApprovalArgs),
+    /// Moves a payroll to a new receiver key, e.g. after the receiver lost
+    /// theirs. The payroll and its payment history are recreated at the new
+    /// receiver's addresses with their cycles and totals, and a started
+    /// payroll's thread is replaced by one paying the new receiver. A payout
+    /// account set by the old receiver is dropped.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer, writable]` The receiver, who pays the new thread's rent
+    /// 2. `[]` The new receiver's key
+    /// 3. `[]` The authority of the Accounting state account, a signer if the accounting requires approval of transfers
+    /// 4. `[writable]` The Accounting state account. The old thread's lamports go to its fee reserve
+    /// 5. `[writable]` The payroll state, closed
+    /// 6. `[writable]` The new payroll state. A pda with seeds [b"payroll".as_ref(), accounting.key.as_ref(), new_receiver.key.as_ref()]
+    /// 7. `[writable]` The payment history account, closed if it exists
+    /// 8. `[writable]` The new payment history account. A pda with seeds [b"history".as_ref(), new_payroll.key.as_ref()]
+    /// 9. `[writable]` The Thread
+    /// 10. `[writable]` The new Thread
+    /// 11. `[]` The Thread program
+    /// 12. `[]` The System Program
+    /// 13. `[]` The payroll mint's vault record, unless it is the accounting's primary mint
+    TransferPayroll,
+    /// Sets how many cycles' worth of pay receivers may have advanced at
+    /// once. Zero disables advances.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The authority of the Accounting state account.
+    /// 1. `[writable]` The Accounting state account.
+    SetAdvanceLimit(AdvanceLimitArgs),
+    /// Pays the receiver part of their next cycles' pay now, with the
+    /// authority's approval. The advance is kept back from the payroll's
+    /// next payments until repaid, and what is outstanding may not exceed
+    /// the accounting's `max_advance_cycles` cycles' worth of pay.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer, writable]` The receiver, credited directly with native SOL advances
+    /// 2. `[signer]` The authority of the Accounting state account.
+    /// 3. `[writable]` The Accounting state account.
+    /// 4. `[writable]` The payroll state
+    /// 5. `[writable]` The vault
+    /// 6. `[writable]` The receiver's payout account, which must exist. For the native mint, the
+    ///    temporary account wrapped SOL is unwrapped through instead. A pda with seeds [b"unwrap".as_ref(), payroll.key.as_ref()]
+    /// 7. `[]` The Token Program
+    /// 8. `[]` The payroll's token mint
+    /// 9. `[writable]` The accounting's rent budget. A pda with seeds [b"rent_budget".as_ref(), accounting.key.as_ref()]
+    /// 10. `[]` The System Program
+    /// 11. `[writable]` The payroll mint's vault record, unless it is the accounting's primary mint
+    AdvanceRequest(AdvanceArgs),
+    /// Queues a one-off bonus or deduction on the payroll's next payment,
+    /// which applies every queued adjustment to its pay after deductions and
+    /// clears them. Deductions only ever take that pay down to zero.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer]` The authority of the Accounting state account.
+    /// 2. `[writable]` The Accounting state account.
+    /// 3. `[writable]` The payroll state
+    /// 4. `[writable]` The payroll mint's vault record, unless it is the accounting's primary mint
+    AddAdjustment(AdjustmentArgs),
+    /// Resets the price a quote-denominated payroll measures slippage
+    /// against to its feed's current price, e.g. after a move past its
+    /// maximum slippage failed its payments. The price must still be fresh
+    /// and certain enough.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 1. `[signer]` The authority of the Accounting state account.
+    /// 2. `[writable]` The Accounting state account.
+    /// 3. `[writable]` The payroll state
+    /// 4. `[writable]` The payroll mint's vault record, unless it is the accounting's primary mint
+    /// 5. `[]` The payroll's price feed
+    RepricePayroll,
+    /// Transfers lamports out of the accounting's fee reserve to the
+    /// authority. The accounting stays rent exempt.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The authority of the Accounting state account.
+    /// 1. `[writable]` The Accounting state account.
+    WithdrawFeeReserve(WithdrawFeeReserveArgs),
+    /// Transfers lamports out of the accounting's rent budget to the
+    /// authority, leaving at least the budget's rent-exempt minimum.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The authority of the Accounting state account.
+    /// 1. `[]` The Accounting state account.
+    /// 2. `[writable]` The rent budget. A pda with seeds [b"rent_budget".as_ref(), accounting.key.as_ref()]
+    /// 3. `[]` The System Program
+    WithdrawRentBudget(WithdrawRentBudgetArgs),
 }
 
-#[derive(BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct DepositArgs {
     pub amount: u64,
 }
 
-#[derive(BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
 pub struct NewPayrollArgs {
     pub amount: u64,
+    /// Number of cycles to pay, or `Payroll::OPEN_ENDED` to pay until the
+    /// payroll is cancelled.
     pub cycles: u64,
     pub schedule: [u8; 30],
+    pub withholding: Vec<DeductionArgs>,
+    /// Decimals `amount` is expressed in. When set, the payroll is only
+    /// created if they match the payroll mint's, like `transfer_checked`.
+    pub decimals: Option<u8>,
+    /// Mint to pay in. Defaults to the accounting's primary mint.
+    pub mint: Option<Pubkey>,
+    /// When set, `amount` is in a quote currency and converted to tokens
+    /// through a price feed at every payment.
+    pub quote: Option<QuoteArgs>,
+    /// Create the payroll even if the vault can't cover every payroll it's
+    /// committed to, this one included.
+    pub allow_overcommit: bool,
+    /// Unix timestamp before which the payroll's cycles pay nothing.
+    pub start_ts: Option<i64>,
+    /// Unix timestamp from which the payroll stops paying.
+    pub end_ts: Option<i64>,
+    /// Pay the cycles `start_ts` and `end_ts` fall within only for the part
+    /// of their period they cover. The cycle after `end_ts` then still pays
+    /// for the time before it.
+    pub prorate: bool,
+}
+
+/// Limits on the price a quote-denominated payroll is converted at. See
+/// `QuotePricing`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct QuoteArgs {
+    pub quote_decimals: u8,
+    pub max_price_age: u64,
+    pub max_confidence_bps: u16,
+    pub max_slippage_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct WithdrawArgs {
+    pub amount: u64,
+    /// Withdraw even if what is left doesn't cover what the vault's payrolls
+    /// are committed to.
+    pub allow_overcommit: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AllowanceArgs {
+    pub allowance: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct FundRentBudgetArgs {
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct FundFeeReserveArgs {
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct WithdrawFeeReserveArgs {
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct WithdrawRentBudgetArgs {
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct NewPayrollBatchArgs {
+    pub payrolls: Vec<NewPayrollArgs>,
+    /// Also start every payroll's thread, as `InitPayment` does.
+    pub start: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ThreadMinBalanceArgs {
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TransferApprovalArgs {
+    pub required: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AdvanceLimitArgs {
+    pub max_cycles: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AdvanceArgs {
+    pub amount: u64,
+}
+
+/// A bonus if `amount` is positive, or a deduction if negative, in tokens of
+/// the payroll mint.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AdjustmentArgs {
+    pub amount: i64,
+    pub memo: [u8; Adjustment::MEMO_LEN],
+}
+
+/// Fields left as `None` are unchanged.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct UpdatePayrollArgs {
+    pub amount: Option<u64>,
+    /// New cycle limit, which may be `Payroll::OPEN_ENDED`.
+    pub cycles: Option<u64>,
+    /// Update the payroll even if the vault can't cover every payroll it's
+    /// committed to afterwards.
+    pub allow_overcommit: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CancelPayrollArgs {
+    /// Cancel even with an advance outstanding, writing off what the
+    /// receiver hasn't repaid.
+    pub allow_write_off: bool,
+}
+
+/// A deduction to withhold from every payment. The destination token
+/// account is passed alongside the instruction's accounts.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct DeductionArgs {
+    pub kind: DeductionKind,
+    pub value: u64,
 }
 
 
@@ -88,6 +557,31 @@ impl ClockPayInstruction {
             2 => Self::unpack_new_payroll_args(rest)?,
             3 => Self::unpack_init_payment_args()?,
             4 => Self::unpack_pay_args()?,
+            5 => Self::unpack_close_history_args()?,
+            6 => Self::unpack_withdraw_args(rest)?,
+            7 => Self::unpack_update_payroll_args(rest)?,
+            8 => Self::unpack_pause_payroll_args()?,
+            9 => Self::unpack_resume_payroll_args()?,
+            10 => Self::unpack_cancel_payroll_args(rest)?,
+            11 => Self::unpack_init_delegated_accounting_args(rest)?,
+            12 => Self::unpack_set_allowance_args(rest)?,
+            13 => Self::unpack_add_mint_args()?,
+            14 => Self::unpack_fund_rent_budget_args(rest)?,
+            15 => Self::unpack_fund_fee_reserve_args(rest)?,
+            16 => Self::unpack_set_thread_min_balance_args(rest)?,
+            17 => Self::unpack_fund_threads_args()?,
+            18 => Self::unpack_sync_args()?,
+            19 => Self::unpack_new_payroll_batch_args(rest)?,
+            20 => Self::unpack_create_and_start_payroll_args(rest)?,
+            21 => Self::unpack_set_payout_account_args()?,
+            22 => Self::unpack_set_transfer_approval_args(rest)?,
+            23 => Self::unpack_transfer_payroll_args()?,
+            24 => Self::unpack_set_advance_limit_args(rest)?,
+            25 => Self::unpack_advance_request_args(rest)?,
+            26 => Self::unpack_add_adjustment_args(rest)?,
+            27 => Self::unpack_reprice_payroll_args()?,
+            28 => Self::unpack_withdraw_fee_reserve_args(rest)?,
+            29 => Self::unpack_withdraw_rent_budget_args(rest)?,
             _ => return Err(InvalidInstruction.into()),
         })
     }
@@ -113,4 +607,897 @@ impl ClockPayInstruction {
     fn unpack_pay_args() -> Result<Self, ProgramError> {
         Ok(Self::Pay)
     }
+
+    fn unpack_close_history_args() -> Result<Self, ProgramError> {
+        Ok(Self::CloseHistory)
+    }
+
+    fn unpack_withdraw_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = WithdrawArgs::try_from_slice(src)?;
+        Ok(Self::Withdraw(unpacked_args))
+    }
+
+    fn unpack_update_payroll_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = UpdatePayrollArgs::try_from_slice(src)?;
+        Ok(Self::UpdatePayroll(unpacked_args))
+    }
+
+    fn unpack_pause_payroll_args() -> Result<Self, ProgramError> {
+        Ok(Self::PausePayroll)
+    }
+
+    fn unpack_resume_payroll_args() -> Result<Self, ProgramError> {
+        Ok(Self::ResumePayroll)
+    }
+
+    fn unpack_cancel_payroll_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = CancelPayrollArgs::try_from_slice(src)?;
+        Ok(Self::CancelPayroll(unpacked_args))
+    }
+
+    fn unpack_init_delegated_accounting_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = AllowanceArgs::try_from_slice(src)?;
+        Ok(Self::InitDelegatedAccounting(unpacked_args))
+    }
+
+    fn unpack_set_allowance_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = AllowanceArgs::try_from_slice(src)?;
+        Ok(Self::SetAllowance(unpacked_args))
+    }
+
+    fn unpack_add_mint_args() -> Result<Self, ProgramError> {
+        Ok(Self::AddMint)
+    }
+
+    fn unpack_fund_rent_budget_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = FundRentBudgetArgs::try_from_slice(src)?;
+        Ok(Self::FundRentBudget(unpacked_args))
+    }
+
+    fn unpack_fund_fee_reserve_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = FundFeeReserveArgs::try_from_slice(src)?;
+        Ok(Self::FundFeeReserve(unpacked_args))
+    }
+
+    fn unpack_set_thread_min_balance_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = ThreadMinBalanceArgs::try_from_slice(src)?;
+        Ok(Self::SetThreadMinBalance(unpacked_args))
+    }
+
+    fn unpack_fund_threads_args() -> Result<Self, ProgramError> {
+        Ok(Self::FundThreads)
+    }
+
+    fn unpack_sync_args() -> Result<Self, ProgramError> {
+        Ok(Self::Sync)
+    }
+
+    fn unpack_new_payroll_batch_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = NewPayrollBatchArgs::try_from_slice(src)?;
+        Ok(Self::NewPayrollBatch(unpacked_args))
+    }
+
+    fn unpack_create_and_start_payroll_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = NewPayrollArgs::try_from_slice(src)?;
+        Ok(Self::CreateAndStartPayroll(unpacked_args))
+    }
+
+    fn unpack_set_payout_account_args() -> Result<Self, ProgramError> {
+        Ok(Self::SetPayoutAccount)
+    }
+
+    fn unpack_set_transfer_approval_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = TransferApprovalArgs::try_from_slice(src)?;
+        Ok(Self::SetTransferApproval(unpacked_args))
+    }
+
+    fn unpack_transfer_payroll_args() -> Result<Self, ProgramError> {
+        Ok(Self::TransferPayroll)
+    }
+
+    fn unpack_set_advance_limit_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = AdvanceLimitArgs::try_from_slice(src)?;
+        Ok(Self::SetAdvanceLimit(unpacked_args))
+    }
+
+    fn unpack_advance_request_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = AdvanceArgs::try_from_slice(src)?;
+        Ok(Self::AdvanceRequest(unpacked_args))
+    }
+
+    fn unpack_add_adjustment_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = AdjustmentArgs::try_from_slice(src)?;
+        Ok(Self::AddAdjustment(unpacked_args))
+    }
+
+    fn unpack_reprice_payroll_args() -> Result<Self, ProgramError> {
+        Ok(Self::RepricePayroll)
+    }
+
+    fn unpack_withdraw_fee_reserve_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = WithdrawFeeReserveArgs::try_from_slice(src)?;
+        Ok(Self::WithdrawFeeReserve(unpacked_args))
+    }
+
+    fn unpack_withdraw_rent_budget_args(src: &[u8]) -> Result<Self, ProgramError> {
+        let unpacked_args = WithdrawRentBudgetArgs::try_from_slice(src)?;
+        Ok(Self::WithdrawRentBudget(unpacked_args))
+    }
+}
+
+/// Creates an `InitAccounting` instruction. `token_program` is the program
+/// that owns `mint`, SPL Token or Token-2022.
+pub fn init_accounting(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, initializer);
+    let vault = get_associated_token_address_with_program_id(&accounting, mint, token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*initializer, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(accounting, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(SystemProgramId, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(AssociatedTokenProgramId, false),
+        ],
+        data: vec![0],
+    }
+}
+
+/// Creates an `InitDelegatedAccounting` instruction.
+pub fn init_delegated_accounting(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    treasury: &Pubkey,
+    allowance: u64,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, initializer);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*initializer, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(accounting, false),
+            AccountMeta::new(*treasury, false),
+            AccountMeta::new_readonly(SystemProgramId, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        data: pack(11, &AllowanceArgs { allowance }),
+    }
+}
+
+/// Creates a `SetAllowance` instruction.
+pub fn set_allowance(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    token_program: &Pubkey,
+    treasury: &Pubkey,
+    allowance: u64,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(accounting, false),
+            AccountMeta::new(*treasury, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        data: pack(12, &AllowanceArgs { allowance }),
+    }
+}
+
+/// Creates a `Deposit` instruction. `mint_vault` is the mint's vault record,
+/// or `None` for the accounting's primary mint. Passing `authority` as
+/// `source` deposits lamports into a native mint vault.
+pub fn deposit(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    source: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let vault = get_associated_token_address_with_program_id(&accounting, mint, token_program);
+
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(*source, false),
+        AccountMeta::new(vault, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(*mint, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+    if source == authority {
+        accounts.push(AccountMeta::new_readonly(SystemProgramId, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(1, &DepositArgs { amount }),
+    }
+}
+
+/// Creates a `NewPayroll` instruction. `mint_vault` is the vault record of
+/// `args.mint`, or `None` for the accounting's primary mint, `price_feed` is
+/// required when `args.quote` is set, and `deduction_destinations` holds one
+/// token account per entry in `args.withholding`.
+pub fn new_payroll(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    args: &NewPayrollArgs,
+    mint_vault: Option<&Pubkey>,
+    price_feed: Option<&Pubkey>,
+    deduction_destinations: &[Pubkey],
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+        AccountMeta::new_readonly(*receiver, false),
+        AccountMeta::new_readonly(SystemProgramId, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+    if let Some(price_feed) = price_feed {
+        accounts.push(AccountMeta::new_readonly(*price_feed, false));
+    }
+    for destination in deduction_destinations {
+        accounts.push(AccountMeta::new_readonly(*destination, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(2, args),
+    }
+}
+
+/// Creates an `InitPayment` instruction. `mint`, `vault` and `token_program`
+/// are those of the payroll's mint, and `mint_vault` its vault record, or
+/// `None` for the accounting's primary mint. `payout_account` is the one the
+/// receiver set with `SetPayoutAccount`, if any.
+#[allow(clippy::too_many_arguments)]
+pub fn init_payment(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    token_program: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    receiver: &Pubkey,
+    payout_account: Option<&Pubkey>,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+    let (history, _) = PaymentHistory::find_address(program_id, &payroll);
+    let receiver_wallet = match payout_account {
+        _ if token::is_native_mint(mint) => Payroll::find_unwrap_address(program_id, &payroll).0,
+        Some(payout_account) => *payout_account,
+        None => get_associated_token_address_with_program_id(receiver, mint, token_program),
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+        AccountMeta::new(*vault, false),
+        AccountMeta::new(receiver_wallet, false),
+        AccountMeta::new(find_thread_address(&payroll, receiver), false),
+        AccountMeta::new_readonly(clockwork_sdk::ID, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(SystemProgramId, false),
+        AccountMeta::new(history, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new_readonly(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: vec![3],
+    }
+}
+
+/// Creates a `NewPayrollBatch` instruction. `accounts` lists, for every entry
+/// of `args.payrolls` in order, the accounts of its `new_payroll` instruction
+/// followed, if `args.start` is set, by those of its `init_payment`.
+pub fn new_payroll_batch(
+    program_id: &Pubkey,
+    args: &NewPayrollBatchArgs,
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(19, args),
+    }
+}
+
+/// Creates a `CreateAndStartPayroll` instruction. Takes the arguments of
+/// `new_payroll` along with the `mint`, `vault` and `token_program` of the
+/// payroll's mint, as `init_payment` does.
+#[allow(clippy::too_many_arguments)]
+pub fn create_and_start_payroll(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    args: &NewPayrollArgs,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    token_program: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    price_feed: Option<&Pubkey>,
+    deduction_destinations: &[Pubkey],
+) -> Instruction {
+    let mut accounts = new_payroll(
+        program_id,
+        authority,
+        receiver,
+        args,
+        mint_vault,
+        price_feed,
+        deduction_destinations,
+    )
+    .accounts;
+    accounts.extend(
+        init_payment(
+            program_id,
+            authority,
+            mint,
+            vault,
+            token_program,
+            mint_vault,
+            receiver,
+            None,
+        )
+        .accounts,
+    );
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(20, args),
+    }
+}
+
+/// Creates a `SetPayoutAccount` instruction. `mint_vault` is the payroll's
+/// vault record, or `None` when it pays in the accounting's primary mint.
+pub fn set_payout_account(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    accounting: &Pubkey,
+    payout_account: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+) -> Instruction {
+    let (payroll, _) = Payroll::find_address(program_id, accounting, receiver);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*receiver, true),
+        AccountMeta::new(payroll, false),
+        AccountMeta::new_readonly(*accounting, false),
+        AccountMeta::new_readonly(*payout_account, false),
+        AccountMeta::new(find_thread_address(&payroll, receiver), false),
+        AccountMeta::new_readonly(clockwork_sdk::ID, false),
+        AccountMeta::new_readonly(SystemProgramId, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new_readonly(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: vec![21],
+    }
+}
+
+/// Creates a `SetTransferApproval` instruction.
+pub fn set_transfer_approval(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    required: bool,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(accounting, false),
+        ],
+        data: pack(22, &TransferApprovalArgs { required }),
+    }
+}
+
+/// Creates a `TransferPayroll` instruction moving the payroll `authority`
+/// pays `receiver` to `new_receiver`. `approved` makes the authority a
+/// signer, for accountings that require it. `mint_vault` is the payroll's
+/// vault record, or `None` when it pays in the accounting's primary mint.
+pub fn transfer_payroll(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    new_receiver: &Pubkey,
+    authority: &Pubkey,
+    approved: bool,
+    mint_vault: Option<&Pubkey>,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+    let (new_payroll, _) = Payroll::find_address(program_id, &accounting, new_receiver);
+    let (history, _) = PaymentHistory::find_address(program_id, &payroll);
+    let (new_history, _) = PaymentHistory::find_address(program_id, &new_payroll);
+
+    let mut accounts = vec![
+        AccountMeta::new(*receiver, true),
+        AccountMeta::new_readonly(*new_receiver, false),
+        AccountMeta::new_readonly(*authority, approved),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+        AccountMeta::new(new_payroll, false),
+        AccountMeta::new(history, false),
+        AccountMeta::new(new_history, false),
+        AccountMeta::new(find_thread_address(&payroll, receiver), false),
+        AccountMeta::new(find_thread_address(&new_payroll, new_receiver), false),
+        AccountMeta::new_readonly(clockwork_sdk::ID, false),
+        AccountMeta::new_readonly(SystemProgramId, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new_readonly(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: vec![23],
+    }
+}
+
+/// Creates a `SetAdvanceLimit` instruction.
+pub fn set_advance_limit(program_id: &Pubkey, authority: &Pubkey, max_cycles: u64) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(accounting, false),
+        ],
+        data: pack(24, &AdvanceLimitArgs { max_cycles }),
+    }
+}
+
+/// Creates an `AdvanceRequest` instruction paying `receiver` `amount` of
+/// the payroll `authority` pays them ahead of time. `mint`, `vault` and
+/// `token_program` are those of the payroll's mint, and `mint_vault` its
+/// vault record, or `None` for the accounting's primary mint.
+/// `payout_account` is the one the receiver set with `SetPayoutAccount`, if
+/// any.
+#[allow(clippy::too_many_arguments)]
+pub fn advance_request(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    token_program: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    payout_account: Option<&Pubkey>,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+    let (rent_budget, _) = Accounting::find_rent_budget_address(program_id, &accounting);
+    let receiver_wallet = match payout_account {
+        _ if token::is_native_mint(mint) => Payroll::find_unwrap_address(program_id, &payroll).0,
+        Some(payout_account) => *payout_account,
+        None => get_associated_token_address_with_program_id(receiver, mint, token_program),
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new(*receiver, true),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+        AccountMeta::new(*vault, false),
+        AccountMeta::new(receiver_wallet, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(rent_budget, false),
+        AccountMeta::new_readonly(SystemProgramId, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(25, &AdvanceArgs { amount }),
+    }
+}
+
+/// Creates an `AddAdjustment` instruction. `mint_vault` is the payroll's
+/// vault record, or `None` when it pays in the accounting's primary mint.
+pub fn add_adjustment(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    args: &AdjustmentArgs,
+    mint_vault: Option<&Pubkey>,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(26, args),
+    }
+}
+
+/// Creates a `RepricePayroll` instruction. `price_feed` is the payroll's
+/// feed, and `mint_vault` its vault record, or `None` when it pays in the
+/// accounting's primary mint.
+pub fn reprice_payroll(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    price_feed: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+    accounts.push(AccountMeta::new_readonly(*price_feed, false));
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: vec![27],
+    }
+}
+
+/// Creates a `CloseHistory` instruction.
+pub fn close_history(program_id: &Pubkey, receiver: &Pubkey, payroll: &Pubkey) -> Instruction {
+    let (history, _) = PaymentHistory::find_address(program_id, payroll);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*receiver, true),
+            AccountMeta::new_readonly(*payroll, false),
+            AccountMeta::new(history, false),
+        ],
+        data: vec![5],
+    }
+}
+
+/// Creates a `Withdraw` instruction. `mint_vault` is the mint's vault record,
+/// or `None` for the accounting's primary mint.
+pub fn withdraw(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    destination: &Pubkey,
+    args: &WithdrawArgs,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let vault = get_associated_token_address_with_program_id(&accounting, mint, token_program);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(*mint, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(6, args),
+    }
+}
+
+/// Creates an `AddMint` instruction.
+pub fn add_mint(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (mint_vault, _) = MintVault::find_address(program_id, &accounting, mint);
+    let vault = get_associated_token_address_with_program_id(&accounting, mint, token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(accounting, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(mint_vault, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(SystemProgramId, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(AssociatedTokenProgramId, false),
+        ],
+        data: vec![13],
+    }
+}
+
+/// Creates a `FundRentBudget` instruction.
+pub fn fund_rent_budget(program_id: &Pubkey, authority: &Pubkey, lamports: u64) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (rent_budget, _) = Accounting::find_rent_budget_address(program_id, &accounting);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(accounting, false),
+            AccountMeta::new(rent_budget, false),
+            AccountMeta::new_readonly(SystemProgramId, false),
+        ],
+        data: pack(14, &FundRentBudgetArgs { lamports }),
+    }
+}
+
+/// Creates a `FundFeeReserve` instruction.
+pub fn fund_fee_reserve(program_id: &Pubkey, authority: &Pubkey, lamports: u64) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(accounting, false),
+            AccountMeta::new_readonly(SystemProgramId, false),
+        ],
+        data: pack(15, &FundFeeReserveArgs { lamports }),
+    }
+}
+
+/// Creates a `WithdrawRentBudget` instruction.
+pub fn withdraw_rent_budget(program_id: &Pubkey, authority: &Pubkey, lamports: u64) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (rent_budget, _) = Accounting::find_rent_budget_address(program_id, &accounting);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(accounting, false),
+            AccountMeta::new(rent_budget, false),
+            AccountMeta::new_readonly(SystemProgramId, false),
+        ],
+        data: pack(29, &WithdrawRentBudgetArgs { lamports }),
+    }
+}
+
+/// Creates a `WithdrawFeeReserve` instruction.
+pub fn withdraw_fee_reserve(program_id: &Pubkey, authority: &Pubkey, lamports: u64) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(accounting, false),
+        ],
+        data: pack(28, &WithdrawFeeReserveArgs { lamports }),
+    }
+}
+
+/// Creates a `SetThreadMinBalance` instruction.
+pub fn set_thread_min_balance(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(accounting, false),
+        ],
+        data: pack(16, &ThreadMinBalanceArgs { lamports }),
+    }
+}
+
+/// Creates a `FundThreads` instruction topping up the threads of `payrolls`,
+/// which must all belong to `accounting`.
+pub fn fund_threads(
+    program_id: &Pubkey,
+    accounting: &Pubkey,
+    payrolls: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(*accounting, false)];
+    for (payroll, thread) in payrolls {
+        accounts.push(AccountMeta::new_readonly(*payroll, false));
+        accounts.push(AccountMeta::new(*thread, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: vec![17],
+    }
+}
+
+/// Creates a `Sync` instruction for the vault of `mint`. `mint_vault` is its
+/// vault record, or `None` for the accounting's primary mint.
+pub fn sync(
+    program_id: &Pubkey,
+    accounting: &Pubkey,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*accounting, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(*vault, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: vec![18],
+    }
+}
+
+/// Creates an `UpdatePayroll` instruction.
+pub fn update_payroll(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    args: &UpdatePayrollArgs,
+    mint_vault: Option<&Pubkey>,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(accounting, false),
+        AccountMeta::new(payroll, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: pack(7, args),
+    }
+}
+
+/// Creates a `PausePayroll` instruction.
+pub fn pause_payroll(program_id: &Pubkey, authority: &Pubkey, receiver: &Pubkey) -> Instruction {
+    payroll_thread_instruction(program_id, authority, receiver, 8)
+}
+
+/// Creates a `ResumePayroll` instruction.
+pub fn resume_payroll(program_id: &Pubkey, authority: &Pubkey, receiver: &Pubkey) -> Instruction {
+    payroll_thread_instruction(program_id, authority, receiver, 9)
+}
+
+/// Creates a `CancelPayroll` instruction. `mint_vault` is the payroll's vault
+/// record, or `None` when it pays in the accounting's primary mint.
+pub fn cancel_payroll(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    allow_write_off: bool,
+) -> Instruction {
+    let mut instruction = payroll_thread_instruction(program_id, authority, receiver, 10);
+    if let Some(mint_vault) = mint_vault {
+        instruction
+            .accounts
+            .push(AccountMeta::new(*mint_vault, false));
+    }
+    instruction.data = pack(10, &CancelPayrollArgs { allow_write_off });
+    instruction
+}
+
+fn payroll_thread_instruction(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    receiver: &Pubkey,
+    tag: u8,
+) -> Instruction {
+    let (accounting, _) = Accounting::find_address(program_id, authority);
+    let (payroll, _) = Payroll::find_address(program_id, &accounting, receiver);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(accounting, false),
+            AccountMeta::new(payroll, false),
+            AccountMeta::new(find_thread_address(&payroll, receiver), false),
+            AccountMeta::new_readonly(clockwork_sdk::ID, false),
+        ],
+        data: vec![tag],
+    }
+}
+
+/// Address of the clockwork thread created for a payroll by `InitPayment`.
+pub fn find_thread_address(payroll: &Pubkey, receiver: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"thread".as_ref(),
+            payroll.as_ref(),
+            Payroll::thread_id(receiver).as_bytes(),
+        ],
+        &clockwork_sdk::ID,
+    )
+    .0
+}
+
+/// Data of the `Pay` instruction a payroll's thread executes every cycle.
+pub fn pay_data() -> Vec<u8> {
+    vec![4]
+}
+
+fn pack<T: BorshSerialize>(tag: u8, args: &T) -> Vec<u8> {
+    let mut data = vec![tag];
+    // Serializing into a Vec cannot fail.
+    args.serialize(&mut data).unwrap();
+    data
 }
@@ -1,7 +1,11 @@
 pub mod error;
+pub mod events;
 pub mod instruction;
+pub mod oracle;
 pub mod processor;
+pub mod schedule;
 pub mod state;
+pub mod token;
 
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
@@ -0,0 +1,246 @@
+//! Price feeds used to convert payroll amounts denominated in a quote
+//! currency into tokens at payment time.
+//!
+//! The processor only depends on the `PriceOracle` trait, and its entrypoint
+//! reads Pyth price accounts through `PythOracle`. `PriceAccountOracle` reads
+//! clockpay's own `PriceAccount` layout as written by the `mock-oracle` test
+//! program, for local banks that have no Pyth feeds.
+
+use crate::{error::ClockPayError, state::QuotePricing};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+/// Pyth oracle program, the only owner trusted for `PythOracle` feeds. A
+/// payroll's authority picks its feed, so a feed written by any other
+/// program could set what its receiver is paid.
+#[cfg(not(feature = "devnet"))]
+pub const PYTH_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+/// Pyth oracle program on devnet.
+#[cfg(feature = "devnet")]
+pub const PYTH_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
+
+/// Address of the `mock-oracle` test program, the only owner trusted for
+/// `PriceAccountOracle` feeds.
+pub const MOCK_ORACLE_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("FC5DyGP3eBUz47WyQQ4Htpz6q5jkdCGysqBN63xQ2TYp");
+
+/// A price of one whole token, `price * 10^expo` quote units, with its
+/// confidence interval in the same units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Price {
+    pub price: u64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+/// Reads the current price out of a price feed account.
+pub trait PriceOracle {
+    fn read_price(feed: &AccountInfo) -> Result<Price, ProgramError>;
+}
+
+/// Oracle for Pyth price accounts. Feeds that aren't trading report their
+/// last traded price, which `check_price` rejects once it's too old.
+pub struct PythOracle;
+
+impl PriceOracle for PythOracle {
+    fn read_price(feed: &AccountInfo) -> Result<Price, ProgramError> {
+        if *feed.owner != PYTH_PROGRAM_ID {
+            return Err(ClockPayError::InvalidPrice.into());
+        }
+        let price = pyth_sdk_solana::load_price_feed_from_account_info(feed)
+            .map_err(|_| ClockPayError::InvalidPrice)?
+            .get_price_unchecked();
+        if price.price <= 0 {
+            return Err(ClockPayError::InvalidPrice.into());
+        }
+
+        Ok(Price {
+            price: price.price as u64,
+            conf: price.conf,
+            expo: price.expo,
+            publish_time: price.publish_time,
+        })
+    }
+}
+
+/// A price feed account in clockpay's own format.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default)]
+pub struct PriceAccount {
+    pub magic: u32,
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+impl PriceAccount {
+    /// "ckpr"
+    pub const MAGIC: u32 = 0x636b_7072;
+    pub const SIZE: usize = 4 + 8 + 8 + 4 + 8;
+}
+
+/// Oracle for feeds stored as a `PriceAccount`.
+pub struct PriceAccountOracle;
+
+impl PriceOracle for PriceAccountOracle {
+    fn read_price(feed: &AccountInfo) -> Result<Price, ProgramError> {
+        if *feed.owner != MOCK_ORACLE_PROGRAM_ID {
+            return Err(ClockPayError::InvalidPrice.into());
+        }
+        let data = feed.try_borrow_data()?;
+        let account = PriceAccount::deserialize(&mut &data[..])?;
+        if account.magic != PriceAccount::MAGIC || account.price <= 0 {
+            return Err(ClockPayError::InvalidPrice.into());
+        }
+
+        Ok(Price {
+            price: account.price as u64,
+            conf: account.conf,
+            expo: account.expo,
+            publish_time: account.publish_time,
+        })
+    }
+}
+
+/// Checks `price` against the payroll's limits and converts `quote_amount`,
+/// in units of `10^-quote_decimals`, into base units of a mint with
+/// `mint_decimals`.
+pub fn quote_to_tokens(
+    pricing: &QuotePricing,
+    price: &Price,
+    now: i64,
+    quote_amount: u64,
+    mint_decimals: u8,
+) -> Result<u64, ClockPayError> {
+    check_price(pricing, price, now)?;
+    let (current, last) = align(
+        price.price,
+        price.expo,
+        pricing.last_price,
+        pricing.last_expo,
+    )
+    .ok_or(ClockPayError::InvalidPrice)?;
+    let slippage = current
+        .abs_diff(last)
+        .checked_mul(BPS)
+        .ok_or(ClockPayError::InvalidPrice)?;
+    if slippage > last.saturating_mul(pricing.max_slippage_bps as u128) {
+        return Err(ClockPayError::PriceSlippageExceeded);
+    }
+
+    convert(
+        quote_amount,
+        pricing.quote_decimals,
+        price.price,
+        price.expo,
+        mint_decimals,
+    )
+}
+
+/// Checks that `price` is as fresh and certain as the payroll's limits
+/// require. Unlike `quote_to_tokens`, doesn't compare it to the last price.
+pub fn check_price(pricing: &QuotePricing, price: &Price, now: i64) -> Result<(), ClockPayError> {
+    // Publish times slightly ahead of the cluster clock count as fresh.
+    let age = now.saturating_sub(price.publish_time).max(0) as u64;
+    if age > pricing.max_price_age {
+        return Err(ClockPayError::StalePrice);
+    }
+    if price.conf as u128 * BPS > price.price as u128 * pricing.max_confidence_bps as u128 {
+        return Err(ClockPayError::UncertainPrice);
+    }
+    Ok(())
+}
+
+/// Converts `quote_amount`, in units of `10^-quote_decimals`, into base units
+/// of a mint with `mint_decimals` at `price * 10^expo` per whole token,
+/// without any of the checks `quote_to_tokens` makes on the price.
+pub fn convert(
+    quote_amount: u64,
+    quote_decimals: u8,
+    price: u64,
+    expo: i32,
+    mint_decimals: u8,
+) -> Result<u64, ClockPayError> {
+    if price == 0 {
+        return Err(ClockPayError::InvalidPrice);
+    }
+    // tokens = quote_amount * 10^-quote_decimals / (price * 10^expo) * 10^mint_decimals
+    let exponent = (mint_decimals as i32)
+        .checked_sub(quote_decimals as i32)
+        .and_then(|exponent| exponent.checked_sub(expo))
+        .ok_or(ClockPayError::InvalidPrice)?;
+    let amount = if exponent >= 0 {
+        (quote_amount as u128)
+            .checked_mul(pow10(exponent.unsigned_abs())?)
+            .map(|scaled| scaled / price as u128)
+    } else {
+        pow10(exponent.unsigned_abs())?
+            .checked_mul(price as u128)
+            .map(|divisor| quote_amount as u128 / divisor)
+    };
+    amount
+        .and_then(|amount| u64::try_from(amount).ok())
+        .ok_or(ClockPayError::InvalidPrice)
+}
+
+const BPS: u128 = 10_000;
+
+fn pow10(exponent: u32) -> Result<u128, ClockPayError> {
+    10u128
+        .checked_pow(exponent)
+        .ok_or(ClockPayError::InvalidPrice)
+}
+
+/// Rescales two prices to the smaller of their exponents.
+fn align(a: u64, a_expo: i32, b: u64, b_expo: i32) -> Option<(u128, u128)> {
+    let expo = a_expo.min(b_expo);
+    let a = (a as u128).checked_mul(10u128.checked_pow(a_expo.checked_sub(expo)? as u32)?)?;
+    let b = (b as u128).checked_mul(10u128.checked_pow(b_expo.checked_sub(expo)? as u32)?)?;
+    Some((a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_scales_up_by_mint_decimals() {
+        // 50.00 quote units at 2.000 a token, in a 6 decimal mint.
+        assert_eq!(convert(5_000, 2, 2_000, -3, 6).unwrap(), 25_000_000);
+    }
+
+    #[test]
+    fn convert_scales_down_when_price_has_more_precision() {
+        // 50.00 quote units at 2 a token, in a 0 decimal mint.
+        assert_eq!(convert(5_000, 2, 2, 0, 0).unwrap(), 25);
+        assert_eq!(convert(5_000, 2, 2, 3, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn convert_refuses_zero_and_overflowing_prices() {
+        assert!(matches!(
+            convert(5_000, 2, 0, -3, 6),
+            Err(ClockPayError::InvalidPrice)
+        ));
+        assert!(matches!(
+            convert(u64::MAX, 0, 1, -11, 9),
+            Err(ClockPayError::InvalidPrice)
+        ));
+    }
+
+    #[test]
+    fn extreme_exponents_are_invalid_prices() {
+        assert!(matches!(
+            convert(5_000, 2, 2, i32::MIN, 6),
+            Err(ClockPayError::InvalidPrice)
+        ));
+        assert!(matches!(
+            convert(5_000, 2, 2, i32::MAX, 0),
+            Err(ClockPayError::InvalidPrice)
+        ));
+        assert_eq!(align(1, i32::MAX, 1, i32::MIN), None);
+    }
+}
@@ -1,8 +1,21 @@
-use crate::state::{Accounting, Payroll};
-use crate::{error::ClockPayError, instruction::ClockPayInstruction};
+use crate::state::{
+    Accounting, Adjustment, Deduction, DeductionKind, FundingMode, MintVault, PaymentHistory,
+    Payroll, QuotePricing, Receipt,
+};
+use crate::{
+    error::ClockPayError,
+    events::ClockPayEvent,
+    instruction::{
+        self, AdjustmentArgs, AdvanceArgs, ClockPayInstruction, NewPayrollArgs,
+        NewPayrollBatchArgs, UpdatePayrollArgs, WithdrawArgs,
+    },
+    oracle::{self, PriceOracle},
+    schedule::{self, Period},
+    token,
+};
 use anchor_lang::context::CpiContext;
 use borsh::{BorshDeserialize, BorshSerialize};
-use clockwork_sdk::state::{ThreadResponse, Trigger};
+use clockwork_sdk::state::{ThreadResponse, ThreadSettings, Trigger};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
@@ -10,22 +23,22 @@ use solana_program::{
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
     system_program::ID as SystemProgramId,
+    sysvar::{clock::Clock, Sysvar},
 };
 use spl_associated_token_account::{
-    instruction::create_associated_token_account, ID as AssociatedTokenProgramId,
-};
-use spl_token::{
-    state::{Account as TokenAccount, Mint as MintAccount},
-    ID as TokenProgramId,
+    instruction::create_associated_token_account_idempotent, ID as AssociatedTokenProgramId,
 };
+use std::slice::Iter;
 
 pub struct Processor;
 impl Processor {
-    pub fn process(
+    /// Processes an instruction, reading quote payrolls' price feeds with `O`.
+    pub fn process<O: PriceOracle>(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         instruction_data: &[u8],
@@ -43,23 +56,128 @@ impl Processor {
             }
             ClockPayInstruction::NewPayroll(args) => {
                 msg!("Instruction: Create new payroll");
-                Self::process_new_payroll(
-                    accounts,
-                    program_id,
-                    args.amount,
-                    args.cycles,
-                    args.schedule,
-                )
+                Self::process_new_payroll::<O>(&mut accounts.iter(), program_id, args)
             }
             ClockPayInstruction::InitPayment => {
                 msg!("Instruction: Make payment");
-                Self::process_init_payment(accounts, program_id)
+                Self::process_init_payment(&mut accounts.iter(), program_id)
             }
             ClockPayInstruction::Pay => {
                 msg!("Instruction: Process payment");
-                Self::process_pay(accounts, program_id)?;
+                if let Err(error) = Self::process_pay::<O>(accounts, program_id) {
+                    if let Some(payroll) = accounts.first() {
+                        ClockPayEvent::PaymentFailed {
+                            payroll: *payroll.key,
+                            error: error.clone().into(),
+                        }
+                        .emit();
+                    }
+                    return Err(error);
+                }
                 Ok(())
             }
+            ClockPayInstruction::CloseHistory => {
+                msg!("Instruction: Close payment history");
+                Self::process_close_history(accounts, program_id)
+            }
+            ClockPayInstruction::Withdraw(args) => {
+                msg!("Instruction: Withdraw");
+                Self::process_withdraw(accounts, program_id, args)
+            }
+            ClockPayInstruction::UpdatePayroll(args) => {
+                msg!("Instruction: Update payroll");
+                Self::process_update_payroll(accounts, program_id, args)
+            }
+            ClockPayInstruction::PausePayroll => {
+                msg!("Instruction: Pause payroll");
+                Self::process_pause_payroll(accounts, program_id)
+            }
+            ClockPayInstruction::ResumePayroll => {
+                msg!("Instruction: Resume payroll");
+                Self::process_resume_payroll(accounts, program_id)
+            }
+            ClockPayInstruction::CancelPayroll(args) => {
+                msg!("Instruction: Cancel payroll");
+                Self::process_cancel_payroll(accounts, program_id, args.allow_write_off)
+            }
+            ClockPayInstruction::InitDelegatedAccounting(args) => {
+                msg!("Instruction: Initialize delegated accounting");
+                Self::process_init_delegated_accounting(accounts, program_id, args.allowance)
+            }
+            ClockPayInstruction::SetAllowance(args) => {
+                msg!("Instruction: Set allowance");
+                Self::process_set_allowance(accounts, program_id, args.allowance)
+            }
+            ClockPayInstruction::AddMint => {
+                msg!("Instruction: Add mint");
+                Self::process_add_mint(accounts, program_id)
+            }
+            ClockPayInstruction::FundRentBudget(args) => {
+                msg!("Instruction: Fund rent budget");
+                Self::process_fund_rent_budget(accounts, program_id, args.lamports)
+            }
+            ClockPayInstruction::FundFeeReserve(args) => {
+                msg!("Instruction: Fund fee reserve");
+                Self::process_fund_fee_reserve(accounts, program_id, args.lamports)
+            }
+            ClockPayInstruction::SetThreadMinBalance(args) => {
+                msg!("Instruction: Set thread minimum balance");
+                Self::process_set_thread_min_balance(accounts, program_id, args.lamports)
+            }
+            ClockPayInstruction::FundThreads => {
+                msg!("Instruction: Fund threads");
+                Self::process_fund_threads(accounts, program_id)
+            }
+            ClockPayInstruction::Sync => {
+                msg!("Instruction: Sync balance");
+                Self::process_sync(accounts, program_id)
+            }
+            ClockPayInstruction::NewPayrollBatch(args) => {
+                msg!("Instruction: Create payroll batch");
+                Self::process_new_payroll_batch::<O>(accounts, program_id, args)
+            }
+            ClockPayInstruction::SetPayoutAccount => {
+                msg!("Instruction: Set payout account");
+                Self::process_set_payout_account(accounts, program_id)
+            }
+            ClockPayInstruction::SetTransferApproval(args) => {
+                msg!("Instruction: Set transfer approval");
+                Self::process_set_transfer_approval(accounts, program_id, args.required)
+            }
+            ClockPayInstruction::TransferPayroll => {
+                msg!("Instruction: Transfer payroll");
+                Self::process_transfer_payroll(accounts, program_id)
+            }
+            ClockPayInstruction::SetAdvanceLimit(args) => {
+                msg!("Instruction: Set advance limit");
+                Self::process_set_advance_limit(accounts, program_id, args.max_cycles)
+            }
+            ClockPayInstruction::AdvanceRequest(args) => {
+                msg!("Instruction: Advance pay");
+                Self::process_advance_request(accounts, program_id, args)
+            }
+            ClockPayInstruction::AddAdjustment(args) => {
+                msg!("Instruction: Add adjustment");
+                Self::process_add_adjustment(accounts, program_id, args)
+            }
+            ClockPayInstruction::RepricePayroll => {
+                msg!("Instruction: Reprice payroll");
+                Self::process_reprice_payroll::<O>(accounts, program_id)
+            }
+            ClockPayInstruction::CreateAndStartPayroll(args) => {
+                msg!("Instruction: Create and start payroll");
+                let account_info_iter = &mut accounts.iter();
+                Self::process_new_payroll::<O>(account_info_iter, program_id, args)?;
+                Self::process_init_payment(account_info_iter, program_id)
+            }
+            ClockPayInstruction::WithdrawFeeReserve(args) => {
+                msg!("Instruction: Withdraw from fee reserve");
+                Self::process_withdraw_fee_reserve(accounts, program_id, args.lamports)
+            }
+            ClockPayInstruction::WithdrawRentBudget(args) => {
+                msg!("Instruction: Withdraw from rent budget");
+                Self::process_withdraw_rent_budget(accounts, program_id, args.lamports)
+            }
         }
     }
 
@@ -71,7 +189,6 @@ impl Processor {
         }
 
         let token_mint = next_account_info(account_info_iter)?;
-        MintAccount::unpack(&token_mint.try_borrow_data()?)?;
 
         let accounting_state = next_account_info(account_info_iter)?;
         let (state_pda, state_bump) = Pubkey::find_program_address(
@@ -87,58 +204,38 @@ impl Processor {
         let system_program = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
         let atoken_program = next_account_info(account_info_iter)?;
-        if *system_program.key != SystemProgramId
-            || *token_program.key != TokenProgramId
-            || *atoken_program.key != AssociatedTokenProgramId
+        if *system_program.key != SystemProgramId || *atoken_program.key != AssociatedTokenProgramId
         {
             return Err(ProgramError::IncorrectProgramId);
         }
+        token::check_token_program(token_program.key)?;
+        let mint_info = token::unpack_mint(token_mint, token_program.key)?;
+        token::check_mint_extensions(token_mint)?;
 
         msg!("Initialize accounting state account.");
-        if **accounting_state.try_borrow_lamports()? > 0 {
-            return Err(ClockPayError::AccountAlreadyInitialized.into());
-        }
-        let state_lamports = Rent::default().minimum_balance(Accounting::SIZE);
-        let create_state_account_ix = solana_program::system_instruction::create_account(
-            initializer.key,
-            accounting_state.key,
-            state_lamports,
-            Accounting::SIZE as u64,
-            program_id,
-        );
         let state_account_seeds = &[
             b"accounting".as_ref(),
             initializer.key.as_ref(),
             &[state_bump],
         ];
-        invoke_signed(
-            &create_state_account_ix,
-            &[
-                initializer.clone(),
-                accounting_state.clone(),
-                system_program.clone(),
-            ],
+        create_pda_account(
+            initializer,
+            accounting_state,
+            system_program,
+            program_id,
+            Accounting::SIZE,
             &[&state_account_seeds[..]],
         )?;
 
         msg!("Create token account vault");
-        let create_vault_account_ix = create_associated_token_account(
-            initializer.key,
-            accounting_state.key,
-            token_mint.key,
-            &TokenProgramId,
-        );
-        invoke(
-            &create_vault_account_ix,
-            &[
-                initializer.clone(),
-                vault_account.clone(),
-                accounting_state.clone(),
-                token_mint.clone(),
-                system_program.clone(),
-                token_program.clone(),
-                atoken_program.clone(),
-            ],
+        let balance = create_vault(
+            initializer,
+            vault_account,
+            accounting_state,
+            token_mint,
+            system_program,
+            token_program,
+            atoken_program,
         )?;
 
         let mut accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
@@ -146,11 +243,21 @@ impl Processor {
         accounting_info.mint = *token_mint.key;
         accounting_info.active_payrolls = 0;
         accounting_info.vault = *vault_account.key;
-        accounting_info.balance = 0;
+        accounting_info.balance = balance;
         accounting_info.active = true;
         accounting_info.bump = state_bump;
+        accounting_info.token_program = *token_program.key;
+        accounting_info.decimals = mint_info.decimals;
         accounting_info.serialize(&mut &mut accounting_state.data.borrow_mut()[..])?;
 
+        ClockPayEvent::AccountingInitialized {
+            accounting: *accounting_state.key,
+            authority: *initializer.key,
+            mint: *token_mint.key,
+            vault: *vault_account.key,
+        }
+        .emit();
+
         Ok(())
     }
 
@@ -177,59 +284,134 @@ impl Processor {
         if state_pda != *accounting_state.key || state_bump != accounting_info.bump {
             return Err(ClockPayError::InvalidAccount.into());
         }
-
-        let authority_token_account = next_account_info(account_info_iter)?;
-        let auth_token_account_info =
-            TokenAccount::unpack(&authority_token_account.try_borrow_data()?)?;
-        if auth_token_account_info.mint != accounting_info.mint {
-            return Err(ClockPayError::WrongMint.into());
+        if accounting_info.funding_mode != FundingMode::Custodial {
+            return Err(ClockPayError::WrongFundingMode.into());
         }
 
+        let authority_token_account = next_account_info(account_info_iter)?;
         let vault_account = next_account_info(account_info_iter)?;
-        if *vault_account.key != accounting_info.vault {
-            return Err(ClockPayError::InvalidAccount.into());
-        }
-
         let token_program = next_account_info(account_info_iter)?;
-        if *token_program.key != TokenProgramId {
+        let token_mint = next_account_info(account_info_iter)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting_state.key,
+            &accounting_info,
+            token_mint.key,
+        )?;
+        if *vault_account.key != mint_vault.vault || *token_program.key != mint_vault.token_program
+        {
             return Err(ClockPayError::InvalidAccount.into());
         }
 
-        msg!("Transfer from authority's token account to vault");
-        let transfer_ix = spl_token::instruction::transfer(
-            &TokenProgramId,
-            authority_token_account.key,
-            vault_account.key,
-            authority.key,
-            &[&authority.key],
-            amount,
-        )?;
+        if authority_token_account.key == authority.key {
+            if !token::is_native_mint(token_mint.key) {
+                return Err(ClockPayError::WrongMint.into());
+            }
+            let system_program = next_account_info(account_info_iter)?;
 
-        invoke(
-            &transfer_ix,
-            &[
-                authority_token_account.clone(),
-                vault_account.clone(),
-                authority.clone(),
-            ],
-        )?;
+            msg!("Wrap lamports into vault");
+            let transfer_ix = solana_program::system_instruction::transfer(
+                authority.key,
+                vault_account.key,
+                amount,
+            );
+            invoke(
+                &transfer_ix,
+                &[
+                    authority.clone(),
+                    vault_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+            token::sync_native(token_program, vault_account)?;
+        } else {
+            let auth_token_account_info =
+                token::unpack_token_account(authority_token_account, token_program.key)?;
+            if auth_token_account_info.mint != mint_vault.mint {
+                return Err(ClockPayError::WrongMint.into());
+            }
+
+            msg!("Transfer from authority's token account to vault");
+            token::transfer_checked(
+                token_program,
+                authority_token_account,
+                token_mint,
+                vault_account,
+                authority,
+                &[],
+                amount,
+                mint_vault.decimals,
+            )?;
+        }
 
-        accounting_info.balance = accounting_info.balance.checked_add(amount).unwrap();
+        // Only what reaches the vault after any transfer fee can be paid out.
+        // The balance is taken from the vault itself, which also picks up
+        // tokens sent to it directly.
+        let received = amount
+            .checked_sub(token::transfer_fee(token_mint, amount)?)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        mint_vault.balance = vault_balance(
+            vault_account,
+            token_program.key,
+            accounting_state.key,
+            accounting_info.funding_mode,
+        )?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
         accounting_info.serialize(&mut &mut accounting_state.data.borrow_mut()[..])?;
+
+        ClockPayEvent::Deposited {
+            accounting: *accounting_state.key,
+            amount: received,
+            balance: mint_vault.balance,
+        }
+        .emit();
         Ok(())
     }
 
-    fn process_new_payroll(
-        accounts: &[AccountInfo],
+    /// Reads the accounts of `NewPayroll` off `account_info_iter`, leaving
+    /// any that follow for the next payroll of a batch.
+    fn process_new_payroll<O: PriceOracle>(
+        account_info_iter: &mut Iter<AccountInfo>,
         program_id: &Pubkey,
-        amount: u64,
-        cycles: u64,
-        schedule: [u8; 30],
+        args: NewPayrollArgs,
     ) -> ProgramResult {
+        let NewPayrollArgs {
+            amount,
+            cycles,
+            schedule,
+            withholding,
+            decimals,
+            mint,
+            quote,
+            allow_overcommit,
+            start_ts,
+            end_ts,
+            prorate,
+        } = args;
         if schedule.len() > Payroll::SCHEDULE_LEN {
             return Err(ClockPayError::ScheduleLengthExceeded.into())
         }
-        let account_info_iter = &mut accounts.iter();
+        if withholding.len() > Payroll::MAX_DEDUCTIONS {
+            return Err(ClockPayError::TooManyDeductions.into());
+        }
+        if cycles == 0 {
+            return Err(ClockPayError::InvalidCycles.into());
+        }
+        let start_ts = start_ts.unwrap_or(0);
+        let end_ts = end_ts.unwrap_or(0);
+        if start_ts < 0
+            || (end_ts != 0 && (end_ts <= start_ts || end_ts <= Clock::get()?.unix_timestamp))
+        {
+            return Err(ClockPayError::InvalidPeriod.into());
+        }
+        if prorate {
+            let schedule = std::str::from_utf8(&schedule)
+                .map_err(|_| ClockPayError::InvalidSchedule)?
+                .trim_end_matches('\0')
+                .trim_end();
+            schedule::parse(schedule).ok_or(ClockPayError::InvalidSchedule)?;
+        }
         let authority = next_account_info(account_info_iter)?;
         if !authority.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
@@ -243,7 +425,7 @@ impl Processor {
             &[b"accounting".as_ref(), authority.key.as_ref()],
             program_id,
         );
-        let accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
+        let mut accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
         if accounting_pda != *accounting_state.key || accounting_bump != accounting_info.bump {
             return Err(ClockPayError::InvalidAccount.into());
         }
@@ -256,6 +438,48 @@ impl Processor {
             return Err(ProgramError::IncorrectProgramId);
         }
 
+        let mint = mint.unwrap_or(accounting_info.mint);
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting_state.key,
+            &accounting_info,
+            &mint,
+        )?;
+
+        let mut pricing = QuotePricing::default();
+        if let Some(quote) = &quote {
+            let price_feed = next_account_info(account_info_iter)?;
+            let price = O::read_price(price_feed)?;
+            pricing = QuotePricing {
+                price_feed: *price_feed.key,
+                quote_decimals: quote.quote_decimals,
+                max_price_age: quote.max_price_age,
+                max_confidence_bps: quote.max_confidence_bps,
+                max_slippage_bps: quote.max_slippage_bps,
+                last_price: price.price,
+                last_expo: price.expo,
+            };
+            // Fails now, rather than at the first payment, on a feed that
+            // is stale or can't convert `amount`.
+            oracle::quote_to_tokens(
+                &pricing,
+                &price,
+                Clock::get()?.unix_timestamp,
+                amount,
+                mint_vault.decimals,
+            )?;
+        }
+        let amount_decimals = match &quote {
+            Some(quote) => quote.quote_decimals,
+            None => mint_vault.decimals,
+        };
+        if let Some(decimals) = decimals {
+            if decimals != amount_decimals {
+                return Err(ClockPayError::WrongDecimals.into());
+            }
+        }
+
         let (payroll_pda, payroll_bump) = Pubkey::find_program_address(
             &[b"payroll", accounting_state.key.as_ref(), receiver.key.as_ref()],
             program_id,
@@ -263,53 +487,99 @@ impl Processor {
         if payroll_pda != *payroll_account.key {
             return Err(ClockPayError::InvalidAccount.into());
         }
-        msg!("Initialize payroll account");
-        if **payroll_account.try_borrow_lamports()? > 0 {
-            return Err(ClockPayError::AccountAlreadyInitialized.into());
+
+        let mut deductions = [Deduction::default(); Payroll::MAX_DEDUCTIONS];
+        let mut total_withheld: u64 = 0;
+        for (deduction, config) in deductions.iter_mut().zip(withholding.iter()) {
+            if config.kind == DeductionKind::BasisPoints
+                && config.value > Deduction::MAX_BASIS_POINTS
+            {
+                return Err(ClockPayError::InvalidDeduction.into());
+            }
+            // A fixed token amount can't be compared to a quote-denominated gross.
+            if config.kind == DeductionKind::Fixed && pricing.is_enabled() {
+                return Err(ClockPayError::InvalidDeduction.into());
+            }
+            let destination = next_account_info(account_info_iter)?;
+            let destination_info =
+                token::unpack_token_account(destination, &mint_vault.token_program)?;
+            if destination_info.mint != mint {
+                return Err(ClockPayError::WrongMint.into());
+            }
+
+            deduction.kind = config.kind;
+            deduction.value = config.value;
+            deduction.destination = *destination.key;
+            total_withheld = deduction
+                .withheld_from(amount)
+                .and_then(|withheld| total_withheld.checked_add(withheld))
+                .ok_or(ClockPayError::DeductionsExceedAmount)?;
         }
-        let lamports = Rent::default().minimum_balance(Payroll::SIZE);
-        let create_payroll_account_ix = solana_program::system_instruction::create_account(
-            authority.key,
-            payroll_account.key,
-            lamports,
-            Payroll::SIZE as u64,
-            program_id,
-        );
+        if total_withheld > amount {
+            return Err(ClockPayError::DeductionsExceedAmount.into());
+        }
+
+        msg!("Initialize payroll account");
         let payroll_account_seeds = &[
             b"payroll".as_ref(),
             accounting_state.key.as_ref(),
             receiver.key.as_ref(),
             &[payroll_bump],
         ];
-        invoke_signed(
-            &create_payroll_account_ix,
-            &[
-                authority.clone(),
-                payroll_account.clone(),
-                system_program.clone(),
-            ],
+        create_pda_account(
+            authority,
+            payroll_account,
+            system_program,
+            program_id,
+            Payroll::SIZE,
             &[&payroll_account_seeds[..]],
         )?;
 
-        msg!("Deserializing payroll...");
         let mut payroll_info = Payroll::try_from_slice(&payroll_account.data.borrow())?;
         payroll_info.accounting = *accounting_state.key;
         payroll_info.active = false;
         payroll_info.amount = amount;
         payroll_info.total_amount_disbursed = 0;
+        payroll_info.deduction_count = withholding.len() as u8;
+        payroll_info.deductions = deductions;
         payroll_info.cron_schedule = schedule;
         payroll_info.receiver = *receiver.key;
         payroll_info.max_cycles = cycles;
         payroll_info.cycles_completed = 0;
         payroll_info.bump = payroll_bump;
-        msg!("Serializing payroll...");
+        payroll_info.mint = mint;
+        payroll_info.pricing = pricing;
+        payroll_info.committed = 0;
+        payroll_info.start_ts = start_ts;
+        payroll_info.end_ts = end_ts;
+        payroll_info.prorate = prorate;
+        recommit(&mut mint_vault, &mut payroll_info)?;
+        if !allow_overcommit && !mint_vault.is_solvent() {
+            return Err(ClockPayError::Insolvent.into());
+        }
         payroll_info.serialize(&mut &mut payroll_account.data.borrow_mut()[..])?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting_state.data.borrow_mut()[..])?;
+
+        ClockPayEvent::PayrollCreated {
+            accounting: *accounting_state.key,
+            payroll: *payroll_account.key,
+            receiver: *receiver.key,
+            amount,
+            max_cycles: cycles,
+        }
+        .emit();
+        report_solvency(accounting_state.key, &mint_vault);
 
         Ok(())
     }
 
-    fn process_init_payment(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
+    /// Reads the accounts of `InitPayment` off `account_info_iter`, like
+    /// `process_new_payroll`.
+    fn process_init_payment(
+        account_info_iter: &mut Iter<AccountInfo>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
         let authority = next_account_info(account_info_iter)?;
         let accounting = next_account_info(account_info_iter)?;
         let payroll = next_account_info(account_info_iter)?;
@@ -319,37 +589,85 @@ impl Processor {
         let thread_program = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
         let system_program = next_account_info(account_info_iter)?;
+        let history = next_account_info(account_info_iter)?;
 
-        let pay_ix = Instruction {
-            program_id: *program_id,
-            accounts: vec![
-                AccountMeta::new(*payroll.key, false),
-                AccountMeta::new(*accounting.key, false),
-                AccountMeta::new(*vault.key, false),
-                AccountMeta::new(*receiver_wallet.key, false),
-                AccountMeta::new(*thread.key, true),
-                AccountMeta::new_readonly(*token_program.key, false),
-            ],
-            data: (4 as u64).to_le_bytes().into(),
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        let (mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+        if *vault.key != mint_vault.vault || *token_program.key != mint_vault.token_program {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        // Payments only ever go to the receiver's payout account, or
+        // straight to the receiver for native SOL.
+        let (expected_wallet, paid_to) = if token::is_native_mint(&payroll_info.mint) {
+            let (unwrap_pda, _) = Payroll::find_unwrap_address(program_id, payroll.key);
+            (unwrap_pda, payroll_info.receiver)
+        } else {
+            let payout_account = payroll_info.payout_address(token_program.key);
+            (payout_account, payout_account)
         };
+        if *receiver_wallet.key != expected_wallet {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let (history_pda, history_bump) =
+            Pubkey::find_program_address(&[b"history".as_ref(), payroll.key.as_ref()], program_id);
+        if history_pda != *history.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        // A payroll that was cancelled and created again keeps its earlier receipts.
+        if history.owner != program_id {
+            msg!("Initialize payment history account");
+            let history_seeds = &[b"history".as_ref(), payroll.key.as_ref(), &[history_bump]];
+            create_pda_account(
+                authority,
+                history,
+                system_program,
+                program_id,
+                PaymentHistory::SIZE,
+                &[&history_seeds[..]],
+            )?;
+            let history_info = PaymentHistory {
+                payroll: *payroll.key,
+                receiver: payroll_info.receiver,
+                receipts_recorded: 0,
+                bump: history_bump,
+            };
+            history_info.serialize(&mut &mut history.data.borrow_mut()[..])?;
+        }
+
+        let pay_ix = pay_instruction(
+            program_id,
+            payroll.key,
+            accounting.key,
+            vault.key,
+            thread.key,
+            token_program.key,
+            history.key,
+            &payroll_info.mint,
+            record.map(|record| record.key),
+            &payroll_info,
+        );
 
-        let mut payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
         let payroll_seeds = &[
             b"payroll".as_ref(),
             payroll_info.accounting.as_ref(),
             payroll_info.receiver.as_ref(),
             &[payroll_info.bump],
         ];
-        let thread_id = &payroll_info.receiver.to_string()[0..10];
+        let thread_id = Payroll::thread_id(&payroll_info.receiver);
 
         let cron_bytes: std::vec::Vec<u8> = payroll_info.cron_schedule.into_iter().filter(|&x| x != 0).collect();
-        let schedule = String::from_utf8(cron_bytes.into()).unwrap();
+        let schedule = String::from_utf8(cron_bytes).map_err(|_| ClockPayError::InvalidSchedule)?;
         let schedule = schedule.trim_end();
 
-        msg!("Schedule: {:?}", schedule);
         msg!("Create thread for pay_ix");
-        msg!("payroll authority: {:?}", payroll.clone().key);
-        msg!("thread_id: {:?}", thread_id);
         clockwork_sdk::cpi::thread_create(
             CpiContext::new_with_signer(
                 (*thread_program).clone(),
@@ -361,7 +679,7 @@ impl Processor {
                 },
                 &[&payroll_seeds[..]],
             ),
-            thread_id.to_string(),
+            thread_id,
             pay_ix.into(),
             Trigger::Cron {
                 schedule: schedule.to_string(),
@@ -369,18 +687,39 @@ impl Processor {
             },
         )?;
 
-        let mut accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
-        accounting_info.active_payrolls = accounting_info.active_payrolls.checked_add(1).unwrap();
+        // `thread_create` only funds the thread with what it requires.
+        let topped_up = top_up_thread(accounting, thread, &mut accounting_info)?;
+
+        accounting_info.active_payrolls = accounting_info
+            .active_payrolls
+            .checked_add(1)
+            .ok_or(ClockPayError::ArithmeticError)?;
         accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
 
         payroll_info.active = true;
         payroll_info.thread = *thread.key;
+        if payroll_info.prorate {
+            // The first cycle pays the period the thread starts in.
+            let schedule = schedule::parse(schedule).ok_or(ClockPayError::InvalidSchedule)?;
+            let now = Clock::get()?.unix_timestamp;
+            payroll_info.last_fire_ts = schedule::last_fire(&schedule, 0, now).unwrap_or(0);
+        }
         payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
 
+        report_thread_top_up(payroll.key, thread.key, topped_up, &accounting_info);
+        report_fee_reserve(accounting.key, &accounting_info);
+
+        ClockPayEvent::PaymentStarted {
+            payroll: *payroll.key,
+            thread: *thread.key,
+            receiver_wallet: paid_to,
+        }
+        .emit();
+
         Ok(())
     }
 
-    fn process_pay(
+    fn process_pay<O: PriceOracle>(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
     ) -> Result<ThreadResponse, ProgramError> {
@@ -389,63 +728,2478 @@ impl Processor {
         let accounting = next_account_info(account_info_iter)?;
         let vault = next_account_info(account_info_iter)?;
         let receiver_wallet = next_account_info(account_info_iter)?;
-        let _thread = next_account_info(account_info_iter)?;
+        let thread = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
+        let history = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let receiver = next_account_info(account_info_iter)?;
+        let rent_budget = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let atoken_program = next_account_info(account_info_iter)?;
 
         let mut accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
         let mut payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
 
-        if payroll_info.cycles_completed >= payroll_info.max_cycles {
-            return Err(ClockPayError::TriedExceedingPaymentLimit.into());
+        if payroll.owner != program_id || accounting.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        // Only the payroll's own thread pays it, on its schedule.
+        if !thread.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *thread.key != payroll_info.thread {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if payroll_info.mint != *token_mint.key {
+            return Err(ClockPayError::WrongMint.into());
+        }
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            token_mint.key,
+        )?;
+        if payroll_info.accounting != *accounting.key
+            || mint_vault.vault != *vault.key
+            || mint_vault.token_program != *token_program.key
+        {
+            return Err(ClockPayError::InvalidAccount.into());
         }
 
-        if payroll.owner != program_id || accounting.owner != program_id {
+        let now = Clock::get()?.unix_timestamp;
+        let period = if payroll_info.prorate {
+            let schedule =
+                schedule::parse(&payroll_info.schedule()).ok_or(ClockPayError::InvalidSchedule)?;
+            let period = Period::after(&schedule, payroll_info.last_fire_ts, now)
+                .ok_or(ClockPayError::InvalidSchedule)?;
+            payroll_info.last_fire_ts = period.end;
+            Some(period)
+        } else {
+            None
+        };
+        let (ended, started) = match &period {
+            // The first cycle after the end date still pays for the part of
+            // its period before it.
+            Some(period) => (
+                payroll_info.end_ts != 0 && period.start >= payroll_info.end_ts,
+                period.overlap(payroll_info.start_ts, payroll_info.end_ts) > 0,
+            ),
+            None => (payroll_info.has_ended(now), payroll_info.has_started(now)),
+        };
+        if payroll_info.is_complete() || ended {
+            // A payroll is settled by its last cycle; this only catches one
+            // whose last cycle ran before settling existed.
+            if !payroll_info.active {
+                if payroll_info.is_complete() {
+                    return Err(ClockPayError::TriedExceedingPaymentLimit.into());
+                }
+                return Err(ClockPayError::PayrollEnded.into());
+            }
+            settle_payroll(&mut accounting_info, &mut payroll_info, &mut mint_vault)?;
+            store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+            accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+            payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+
+            ClockPayEvent::PayrollSettled {
+                payroll: *payroll.key,
+                cycles_completed: payroll_info.cycles_completed,
+                total_amount_disbursed: payroll_info.total_amount_disbursed,
+            }
+            .emit();
+            return Ok(ThreadResponse::default());
+        }
+        if !started {
+            msg!("Payroll starts at {}", payroll_info.start_ts);
+            payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+            return Ok(ThreadResponse::default());
+        }
+        // Set only for a cycle that covers part of its period.
+        let proration = period
+            .map(|period| {
+                let worked = period.overlap(payroll_info.start_ts, payroll_info.end_ts);
+                (worked, period.length())
+            })
+            .filter(|&(worked, length)| worked < length);
+
+        let quote_price = if payroll_info.pricing.is_enabled() {
+            let price_feed = next_account_info(account_info_iter)?;
+            if *price_feed.key != payroll_info.pricing.price_feed {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            Some(O::read_price(price_feed)?)
+        } else {
+            None
+        };
+        let gross_amount = match &quote_price {
+            Some(price) => oracle::quote_to_tokens(
+                &payroll_info.pricing,
+                price,
+                now,
+                payroll_info.amount,
+                mint_vault.decimals,
+            )?,
+            None => payroll_info.amount,
+        };
+        let gross_amount = match proration {
+            Some((worked, length)) => schedule::prorate(gross_amount, worked, length)
+                .ok_or(ClockPayError::ArithmeticError)?,
+            None => gross_amount,
+        };
+
+        let adjustment = payroll_info
+            .adjustment_total()
+            .ok_or(ClockPayError::ArithmeticError)?;
+        let required = gross_amount
+            .checked_add(adjustment.max(0) as u64)
+            .ok_or(ClockPayError::ArithmeticError)?;
+
+        let vault_info = token::unpack_token_account(vault, token_program.key)?;
+        if vault_info.amount < required {
+            return Err(ClockPayError::InsufficientFunds.into());
+        }
+        if accounting_info.funding_mode == FundingMode::Delegated
+            && (vault_info.delegate != COption::Some(*accounting.key)
+                || vault_info.delegated_amount < required
+                || accounting_info.balance < required)
+        {
+            return Err(ClockPayError::AllowanceExceeded.into());
+        }
+
+        let (rent_budget_pda, rent_budget_bump) =
+            Accounting::find_rent_budget_address(program_id, accounting.key);
+        if *receiver.key != payroll_info.receiver || rent_budget_pda != *rent_budget.key {
             return Err(ClockPayError::InvalidAccount.into());
         }
-        let receiver_wallet_info = TokenAccount::unpack(&receiver_wallet.try_borrow_data()?)?;
-        if receiver_wallet_info.owner != payroll_info.receiver {
-            return Err(ClockPayError::WrongTokenAccountOwner.into());
+        if *system_program.key != SystemProgramId || *atoken_program.key != AssociatedTokenProgramId
+        {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let rent_budget_seeds = &[
+            b"rent_budget".as_ref(),
+            accounting.key.as_ref(),
+            &[rent_budget_bump],
+        ];
+
+        // Native SOL is unwrapped into the receiver's own system account
+        // instead of being paid to a token account.
+        let mut receiver_account_created = false;
+        let unwrap_bump = if token::is_native_mint(token_mint.key) {
+            let (unwrap_pda, unwrap_bump) = Payroll::find_unwrap_address(program_id, payroll.key);
+            if unwrap_pda != *receiver_wallet.key {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            Some(unwrap_bump)
+        } else {
+            if *receiver_wallet.key != payroll_info.payout_address(token_program.key) {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            // Only the associated token account is created on demand; one set
+            // with `SetPayoutAccount` must already exist.
+            receiver_account_created =
+                payroll_info.payout_account == Pubkey::default() && receiver_wallet.data_is_empty();
+            if receiver_account_created {
+                msg!("Create receiver token account");
+                let create_receiver_account_ix = create_associated_token_account_idempotent(
+                    rent_budget.key,
+                    receiver.key,
+                    token_mint.key,
+                    token_program.key,
+                );
+                invoke_signed(
+                    &create_receiver_account_ix,
+                    &[
+                        rent_budget.clone(),
+                        receiver_wallet.clone(),
+                        receiver.clone(),
+                        token_mint.clone(),
+                        system_program.clone(),
+                        token_program.clone(),
+                        atoken_program.clone(),
+                    ],
+                    &[&rent_budget_seeds[..]],
+                )?;
+            }
+            let receiver_wallet_info =
+                token::unpack_token_account(receiver_wallet, token_program.key)?;
+            if receiver_wallet_info.owner != payroll_info.receiver {
+                return Err(ClockPayError::WrongTokenAccountOwner.into());
+            }
+            if receiver_wallet_info.mint != payroll_info.mint {
+                return Err(ClockPayError::WrongMint.into());
+            }
+            None
+        };
+
+        if history.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let mut history_info = PaymentHistory::deserialize(
+            &mut &history.data.borrow()[..PaymentHistory::HEADER_SIZE],
+        )?;
+        if history_info.payroll != *payroll.key {
+            return Err(ClockPayError::InvalidAccount.into());
         }
 
-        msg!("Transferring payment to {:?}", *receiver_wallet);
         let accounting_seeds = &[
             b"accounting".as_ref(),
             accounting_info.authority.as_ref(),
             &[accounting_info.bump],
         ];
-        let transfer_ix = spl_token::instruction::transfer(
-            &TokenProgramId,
-            vault.key,
-            receiver_wallet.key,
-            accounting.key,
-            &[&accounting.key],
-            payroll_info.amount,
-        )?;
 
-        invoke_signed(
-            &transfer_ix,
-            &[
-                vault.clone(),
-                receiver_wallet.clone(),
-                accounting.clone(),
-                token_program.clone(),
-            ],
-            &[&accounting_seeds[..]],
-        )?;
+        let mut net_amount = gross_amount;
+        let mut transfer_fees: u64 = 0;
+        let mut withheld_amounts = [0; Payroll::MAX_DEDUCTIONS];
+        let deduction_count = payroll_info.deduction_count as usize;
+        for (deduction, withheld_amount) in payroll_info.deductions[..deduction_count]
+            .iter_mut()
+            .zip(withheld_amounts.iter_mut())
+        {
+            let destination = next_account_info(account_info_iter)?;
+            if *destination.key != deduction.destination {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let mut withheld = deduction
+                .withheld_from(gross_amount)
+                .ok_or(ClockPayError::DeductionsExceedAmount)?;
+            if let (DeductionKind::Fixed, Some((worked, length))) = (deduction.kind, proration) {
+                withheld = schedule::prorate(withheld, worked, length)
+                    .ok_or(ClockPayError::ArithmeticError)?;
+            }
+            net_amount = net_amount
+                .checked_sub(withheld)
+                .ok_or(ClockPayError::DeductionsExceedAmount)?;
+
+            let fee = transfer_from_vault(
+                token_program,
+                vault,
+                token_mint,
+                destination,
+                accounting,
+                &accounting_seeds[..],
+                withheld,
+                mint_vault.decimals,
+            )?;
+            transfer_fees = transfer_fees
+                .checked_add(fee)
+                .ok_or(ClockPayError::ArithmeticError)?;
+            deduction.total_withheld = deduction
+                .total_withheld
+                .checked_add(withheld)
+                .ok_or(ClockPayError::ArithmeticError)?;
+            *withheld_amount = withheld;
+        }
+
+        // Adjustments apply to the pay left after deductions, and can only
+        // take it down to zero.
+        let applied = (adjustment as i128).max(-(net_amount as i128));
+        net_amount = (net_amount as i128 + applied)
+            .try_into()
+            .map_err(|_| ClockPayError::ArithmeticError)?;
+        let applied = applied as i64;
+        payroll_info.adjustment_count = 0;
+        payroll_info.adjustments = Default::default();
+
+        // An advance is repaid out of what is left after deductions, which
+        // stays in the vault.
+        let repaid = net_amount.min(payroll_info.advance_outstanding);
+        net_amount -= repaid;
+        payroll_info.advance_outstanding -= repaid;
+
+        let paid_to = match unwrap_bump {
+            Some(unwrap_bump) => {
+                let unwrap_seeds = &[b"unwrap".as_ref(), payroll.key.as_ref(), &[unwrap_bump]];
+                unwrap_to_receiver(
+                    token_program,
+                    vault,
+                    token_mint,
+                    receiver_wallet,
+                    receiver,
+                    accounting,
+                    rent_budget,
+                    system_program,
+                    &[
+                        &accounting_seeds[..],
+                        &rent_budget_seeds[..],
+                        &unwrap_seeds[..],
+                    ],
+                    net_amount,
+                    mint_vault.decimals,
+                )?;
+                *receiver.key
+            }
+            None => {
+                let fee = transfer_from_vault(
+                    token_program,
+                    vault,
+                    token_mint,
+                    receiver_wallet,
+                    accounting,
+                    &accounting_seeds[..],
+                    net_amount,
+                    mint_vault.decimals,
+                )?;
+                transfer_fees = transfer_fees
+                    .checked_add(fee)
+                    .ok_or(ClockPayError::ArithmeticError)?;
+                *receiver_wallet.key
+            }
+        };
 
-        accounting_info.balance = accounting_info
-            .balance
-            .checked_sub(payroll_info.amount)
-            .unwrap();
+        mint_vault.balance = vault_balance(
+            vault,
+            token_program.key,
+            accounting.key,
+            accounting_info.funding_mode,
+        )?;
+        let disbursed: u64 = (gross_amount as i128 + applied as i128)
+            .try_into()
+            .map_err(|_| ClockPayError::ArithmeticError)?;
         payroll_info.total_amount_disbursed = payroll_info
             .total_amount_disbursed
-            .checked_add(payroll_info.amount)
-            .unwrap();
-        payroll_info.cycles_completed = payroll_info.cycles_completed.checked_add(1).unwrap();
+            .checked_add(disbursed)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        payroll_info.cycles_completed = payroll_info
+            .cycles_completed
+            .checked_add(1)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        if let Some(price) = &quote_price {
+            payroll_info.pricing.last_price = price.price;
+            payroll_info.pricing.last_expo = price.expo;
+        }
+        recommit(&mut mint_vault, &mut payroll_info)?;
+        // The thread can't be paused from its own run, so the payroll settles
+        // here and any later fires fail.
+        let settled = payroll_info.active && !payroll_info.has_cycles_left(now);
+        if settled {
+            settle_payroll(&mut accounting_info, &mut payroll_info, &mut mint_vault)?;
+        }
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+
+        let receipt = Receipt {
+            cycle: payroll_info.cycles_completed,
+            timestamp: now,
+            gross_amount,
+            deductions: withheld_amounts,
+            receiver_wallet: paid_to,
+            thread: *thread.key,
+            adjustment: applied,
+        };
+        let receipt_offset = PaymentHistory::receipt_offset(history_info.receipts_recorded);
+        receipt.serialize(&mut &mut history.data.borrow_mut()[receipt_offset..])?;
+        history_info.receipts_recorded = history_info
+            .receipts_recorded
+            .checked_add(1)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        history_info.serialize(&mut &mut history.data.borrow_mut()[..])?;
 
+        let topped_up = if settled {
+            0
+        } else {
+            top_up_thread(accounting, thread, &mut accounting_info)?
+        };
         accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
         payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
 
+        if receiver_account_created {
+            ClockPayEvent::ReceiverAccountCreated {
+                payroll: *payroll.key,
+                receiver_wallet: *receiver_wallet.key,
+            }
+            .emit();
+        }
+        ClockPayEvent::Paid {
+            payroll: *payroll.key,
+            receiver_wallet: receipt.receiver_wallet,
+            thread: receipt.thread,
+            cycle: receipt.cycle,
+            gross_amount,
+            net_amount,
+            withheld: withheld_amounts,
+            timestamp: receipt.timestamp,
+            adjustment: applied,
+        }
+        .emit();
+        if transfer_fees > 0 {
+            ClockPayEvent::TransferFeesCharged {
+                payroll: *payroll.key,
+                cycle: receipt.cycle,
+                fees: transfer_fees,
+            }
+            .emit();
+        }
+        if repaid > 0 {
+            ClockPayEvent::AdvanceRepaid {
+                payroll: *payroll.key,
+                cycle: receipt.cycle,
+                amount: repaid,
+                outstanding: payroll_info.advance_outstanding,
+            }
+            .emit();
+        }
+        if let Some((worked, length)) = proration {
+            ClockPayEvent::CycleProrated {
+                payroll: *payroll.key,
+                cycle: receipt.cycle,
+                worked,
+                period: length,
+                gross_amount,
+            }
+            .emit();
+        }
+        if let Some(price) = quote_price {
+            ClockPayEvent::QuoteConverted {
+                payroll: *payroll.key,
+                cycle: receipt.cycle,
+                quote_amount: payroll_info.amount,
+                price: price.price,
+                expo: price.expo,
+            }
+            .emit();
+        }
+        if settled {
+            ClockPayEvent::PayrollSettled {
+                payroll: *payroll.key,
+                cycles_completed: payroll_info.cycles_completed,
+                total_amount_disbursed: payroll_info.total_amount_disbursed,
+            }
+            .emit();
+        }
+        report_thread_top_up(payroll.key, thread.key, topped_up, &accounting_info);
+        report_fee_reserve(accounting.key, &accounting_info);
+        report_solvency(accounting.key, &mint_vault);
+
         Ok(ThreadResponse::default())
     }
+
+    fn process_close_history(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let receiver = next_account_info(account_info_iter)?;
+        if !receiver.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let payroll = next_account_info(account_info_iter)?;
+        let history = next_account_info(account_info_iter)?;
+        if history.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        let history_info = PaymentHistory::deserialize(
+            &mut &history.data.borrow()[..PaymentHistory::HEADER_SIZE],
+        )?;
+        if history_info.payroll != *payroll.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if history_info.receiver != *receiver.key {
+            return Err(ClockPayError::WrongAuthority.into());
+        }
+        // A cancelled payroll has been closed; otherwise all its cycles must
+        // be paid, or its end date have passed.
+        if payroll.owner == program_id {
+            let payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
+            if !payroll_info.is_complete() && !payroll_info.has_ended(Clock::get()?.unix_timestamp)
+            {
+                return Err(ClockPayError::PaymentsOutstanding.into());
+            }
+        }
+
+        msg!("Closing payment history, refunding rent to receiver");
+        close_account(history, receiver)?;
+
+        ClockPayEvent::HistoryClosed {
+            payroll: *payroll.key,
+            receiver: *receiver.key,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    fn process_withdraw(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        args: WithdrawArgs,
+    ) -> ProgramResult {
+        let WithdrawArgs {
+            amount,
+            allow_overcommit,
+        } = args;
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let accounting_state = next_account_info(account_info_iter)?;
+        if accounting_state.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let (state_pda, state_bump) = Accounting::find_address(program_id, authority.key);
+        let mut accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
+        if state_pda != *accounting_state.key || state_bump != accounting_info.bump {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if accounting_info.funding_mode != FundingMode::Custodial {
+            return Err(ClockPayError::WrongFundingMode.into());
+        }
+
+        let vault_account = next_account_info(account_info_iter)?;
+        let destination = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting_state.key,
+            &accounting_info,
+            token_mint.key,
+        )?;
+        if *vault_account.key != mint_vault.vault || *token_program.key != mint_vault.token_program
+        {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let destination_info = token::unpack_token_account(destination, token_program.key)?;
+        if destination_info.mint != mint_vault.mint {
+            return Err(ClockPayError::WrongMint.into());
+        }
+
+        let available = vault_balance(
+            vault_account,
+            token_program.key,
+            accounting_state.key,
+            accounting_info.funding_mode,
+        )?;
+        if available < amount {
+            return Err(ClockPayError::InsufficientFunds.into());
+        }
+        if !allow_overcommit && available - amount < mint_vault.committed_amount {
+            return Err(ClockPayError::Insolvent.into());
+        }
+
+        msg!("Transfer from vault to authority's token account");
+        let accounting_seeds = &[
+            b"accounting".as_ref(),
+            accounting_info.authority.as_ref(),
+            &[accounting_info.bump],
+        ];
+        transfer_from_vault(
+            token_program,
+            vault_account,
+            token_mint,
+            destination,
+            accounting_state,
+            &accounting_seeds[..],
+            amount,
+            mint_vault.decimals,
+        )?;
+        mint_vault.balance = vault_balance(
+            vault_account,
+            token_program.key,
+            accounting_state.key,
+            accounting_info.funding_mode,
+        )?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting_state.data.borrow_mut()[..])?;
+
+        ClockPayEvent::Withdrawn {
+            accounting: *accounting_state.key,
+            amount,
+            balance: mint_vault.balance,
+        }
+        .emit();
+        report_solvency(accounting_state.key, &mint_vault);
+        Ok(())
+    }
+
+    fn process_update_payroll(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        args: UpdatePayrollArgs,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+
+        if let Some(amount) = args.amount {
+            let total_withheld = payroll_info
+                .active_deductions()
+                .iter()
+                .try_fold(0u64, |total, deduction| {
+                    deduction
+                        .withheld_from(amount)
+                        .and_then(|withheld| total.checked_add(withheld))
+                })
+                .ok_or(ClockPayError::DeductionsExceedAmount)?;
+            if total_withheld > amount {
+                return Err(ClockPayError::DeductionsExceedAmount.into());
+            }
+            payroll_info.amount = amount;
+        }
+        if let Some(cycles) = args.cycles {
+            if cycles == 0 {
+                return Err(ClockPayError::InvalidCycles.into());
+            }
+            if cycles < payroll_info.cycles_completed {
+                return Err(ClockPayError::TriedExceedingPaymentLimit.into());
+            }
+            payroll_info.max_cycles = cycles;
+        }
+        recommit(&mut mint_vault, &mut payroll_info)?;
+        if !args.allow_overcommit && !mint_vault.is_solvent() {
+            return Err(ClockPayError::Insolvent.into());
+        }
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::PayrollUpdated {
+            payroll: *payroll.key,
+            amount: payroll_info.amount,
+            max_cycles: payroll_info.max_cycles,
+        }
+        .emit();
+        report_solvency(accounting.key, &mint_vault);
+        Ok(())
+    }
+
+    fn process_pause_payroll(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let thread = next_account_info(account_info_iter)?;
+        let thread_program = next_account_info(account_info_iter)?;
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        if !payroll_info.active || *thread.key != payroll_info.thread {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        let payroll_seeds = &[
+            b"payroll".as_ref(),
+            payroll_info.accounting.as_ref(),
+            payroll_info.receiver.as_ref(),
+            &[payroll_info.bump],
+        ];
+        clockwork_sdk::cpi::thread_pause(CpiContext::new_with_signer(
+            thread_program.clone(),
+            clockwork_sdk::cpi::ThreadPause {
+                authority: payroll.clone(),
+                thread: thread.clone(),
+            },
+            &[&payroll_seeds[..]],
+        ))?;
+
+        accounting_info.active_payrolls = accounting_info
+            .active_payrolls
+            .checked_sub(1)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+        payroll_info.active = false;
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+
+        ClockPayEvent::PayrollPaused {
+            payroll: *payroll.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_resume_payroll(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let thread = next_account_info(account_info_iter)?;
+        let thread_program = next_account_info(account_info_iter)?;
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        if payroll_info.active
+            || payroll_info.thread == Pubkey::default()
+            || *thread.key != payroll_info.thread
+        {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        // A settled payroll has nothing left to pay.
+        if !payroll_info.has_cycles_left(Clock::get()?.unix_timestamp) {
+            return Err(ClockPayError::TriedExceedingPaymentLimit.into());
+        }
+
+        let payroll_seeds = &[
+            b"payroll".as_ref(),
+            payroll_info.accounting.as_ref(),
+            payroll_info.receiver.as_ref(),
+            &[payroll_info.bump],
+        ];
+        clockwork_sdk::cpi::thread_resume(CpiContext::new_with_signer(
+            thread_program.clone(),
+            clockwork_sdk::cpi::ThreadResume {
+                authority: payroll.clone(),
+                thread: thread.clone(),
+            },
+            &[&payroll_seeds[..]],
+        ))?;
+
+        accounting_info.active_payrolls = accounting_info
+            .active_payrolls
+            .checked_add(1)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+        payroll_info.active = true;
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+
+        ClockPayEvent::PayrollResumed {
+            payroll: *payroll.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_cancel_payroll(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        allow_write_off: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let thread = next_account_info(account_info_iter)?;
+        let thread_program = next_account_info(account_info_iter)?;
+        let (mut accounting_info, payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+        // The advance is only repaid out of the payroll's later payments, so
+        // cancelling forgoes the rest of it.
+        if payroll_info.advance_outstanding > 0 && !allow_write_off {
+            return Err(ClockPayError::AdvanceOutstanding.into());
+        }
+
+        if payroll_info.thread != Pubkey::default() {
+            if *thread.key != payroll_info.thread {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let payroll_seeds = &[
+                b"payroll".as_ref(),
+                payroll_info.accounting.as_ref(),
+                payroll_info.receiver.as_ref(),
+                &[payroll_info.bump],
+            ];
+            let before = accounting.lamports();
+            msg!("Delete payroll thread");
+            clockwork_sdk::cpi::thread_delete(CpiContext::new_with_signer(
+                thread_program.clone(),
+                clockwork_sdk::cpi::ThreadDelete {
+                    authority: payroll.clone(),
+                    close_to: accounting.clone(),
+                    thread: thread.clone(),
+                },
+                &[&payroll_seeds[..]],
+            ))?;
+            accounting_info.fee_reserve = accounting_info
+                .fee_reserve
+                .checked_add(accounting.lamports().saturating_sub(before))
+                .ok_or(ClockPayError::ArithmeticError)?;
+        }
+
+        if payroll_info.active {
+            accounting_info.active_payrolls = accounting_info
+                .active_payrolls
+                .checked_sub(1)
+                .ok_or(ClockPayError::ArithmeticError)?;
+        }
+        mint_vault.committed_amount = mint_vault
+            .committed_amount
+            .saturating_sub(payroll_info.committed);
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        msg!("Close payroll account");
+        close_account(payroll, authority)?;
+
+        if payroll_info.advance_outstanding > 0 {
+            ClockPayEvent::AdvanceWrittenOff {
+                payroll: *payroll.key,
+                amount: payroll_info.advance_outstanding,
+            }
+            .emit();
+        }
+        ClockPayEvent::PayrollCancelled {
+            payroll: *payroll.key,
+            cycles_completed: payroll_info.cycles_completed,
+            total_amount_disbursed: payroll_info.total_amount_disbursed,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_init_delegated_accounting(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        allowance: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let token_mint = next_account_info(account_info_iter)?;
+
+        let accounting_state = next_account_info(account_info_iter)?;
+        let (state_pda, state_bump) = Accounting::find_address(program_id, initializer.key);
+        if state_pda != *accounting_state.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        let treasury = next_account_info(account_info_iter)?;
+
+        let system_program = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        if *system_program.key != SystemProgramId {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        token::check_token_program(token_program.key)?;
+        let mint_info = token::unpack_mint(token_mint, token_program.key)?;
+        token::check_mint_extensions(token_mint)?;
+
+        let treasury_info = token::unpack_token_account(treasury, token_program.key)?;
+        if treasury_info.owner != *initializer.key {
+            return Err(ClockPayError::WrongTokenAccountOwner.into());
+        }
+        if treasury_info.mint != *token_mint.key {
+            return Err(ClockPayError::WrongMint.into());
+        }
+
+        msg!("Initialize accounting state account.");
+        create_pda_account(
+            initializer,
+            accounting_state,
+            system_program,
+            program_id,
+            Accounting::SIZE,
+            &[&[
+                b"accounting".as_ref(),
+                initializer.key.as_ref(),
+                &[state_bump],
+            ]],
+        )?;
+
+        msg!("Approve accounting as delegate on treasury");
+        approve_delegate(
+            token_program,
+            treasury,
+            accounting_state,
+            initializer,
+            allowance,
+        )?;
+
+        let mut accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
+        accounting_info.authority = *initializer.key;
+        accounting_info.mint = *token_mint.key;
+        accounting_info.active_payrolls = 0;
+        accounting_info.vault = *treasury.key;
+        accounting_info.balance = allowance;
+        accounting_info.active = true;
+        accounting_info.bump = state_bump;
+        accounting_info.funding_mode = FundingMode::Delegated;
+        accounting_info.token_program = *token_program.key;
+        accounting_info.decimals = mint_info.decimals;
+        accounting_info.serialize(&mut &mut accounting_state.data.borrow_mut()[..])?;
+
+        ClockPayEvent::AccountingInitialized {
+            accounting: *accounting_state.key,
+            authority: *initializer.key,
+            mint: *token_mint.key,
+            vault: *treasury.key,
+        }
+        .emit();
+        ClockPayEvent::AllowanceSet {
+            accounting: *accounting_state.key,
+            treasury: *treasury.key,
+            allowance,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_set_allowance(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        allowance: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let accounting_state = next_account_info(account_info_iter)?;
+        if accounting_state.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let (state_pda, state_bump) = Accounting::find_address(program_id, authority.key);
+        let mut accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
+        if state_pda != *accounting_state.key || state_bump != accounting_info.bump {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if accounting_info.funding_mode != FundingMode::Delegated {
+            return Err(ClockPayError::WrongFundingMode.into());
+        }
+
+        let treasury = next_account_info(account_info_iter)?;
+        if *treasury.key != accounting_info.vault {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        let token_program = next_account_info(account_info_iter)?;
+        if *token_program.key != accounting_info.token_program {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        approve_delegate(
+            token_program,
+            treasury,
+            accounting_state,
+            authority,
+            allowance,
+        )?;
+        accounting_info.balance = allowance;
+        accounting_info.serialize(&mut &mut accounting_state.data.borrow_mut()[..])?;
+
+        ClockPayEvent::AllowanceSet {
+            accounting: *accounting_state.key,
+            treasury: *treasury.key,
+            allowance,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_add_mint(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let accounting_state = next_account_info(account_info_iter)?;
+        if accounting_state.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let (state_pda, state_bump) = Accounting::find_address(program_id, authority.key);
+        let accounting_info = Accounting::try_from_slice(&accounting_state.data.borrow())?;
+        if state_pda != *accounting_state.key || state_bump != accounting_info.bump {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if accounting_info.funding_mode != FundingMode::Custodial {
+            return Err(ClockPayError::WrongFundingMode.into());
+        }
+
+        let token_mint = next_account_info(account_info_iter)?;
+        if *token_mint.key == accounting_info.mint {
+            return Err(ClockPayError::AccountAlreadyInitialized.into());
+        }
+        let mint_vault_account = next_account_info(account_info_iter)?;
+        let (mint_vault_pda, mint_vault_bump) =
+            MintVault::find_address(program_id, accounting_state.key, token_mint.key);
+        if mint_vault_pda != *mint_vault_account.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let vault_account = next_account_info(account_info_iter)?;
+
+        let system_program = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let atoken_program = next_account_info(account_info_iter)?;
+        if *system_program.key != SystemProgramId || *atoken_program.key != AssociatedTokenProgramId
+        {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        token::check_token_program(token_program.key)?;
+        let mint_info = token::unpack_mint(token_mint, token_program.key)?;
+        token::check_mint_extensions(token_mint)?;
+
+        msg!("Initialize mint vault record");
+        create_pda_account(
+            authority,
+            mint_vault_account,
+            system_program,
+            program_id,
+            MintVault::SIZE,
+            &[&[
+                b"vault".as_ref(),
+                accounting_state.key.as_ref(),
+                token_mint.key.as_ref(),
+                &[mint_vault_bump],
+            ]],
+        )?;
+
+        msg!("Create token account vault");
+        let balance = create_vault(
+            authority,
+            vault_account,
+            accounting_state,
+            token_mint,
+            system_program,
+            token_program,
+            atoken_program,
+        )?;
+
+        let mint_vault = MintVault {
+            accounting: *accounting_state.key,
+            mint: *token_mint.key,
+            token_program: *token_program.key,
+            decimals: mint_info.decimals,
+            vault: *vault_account.key,
+            balance,
+            bump: mint_vault_bump,
+            committed_amount: 0,
+        };
+        mint_vault.serialize(&mut &mut mint_vault_account.data.borrow_mut()[..])?;
+
+        ClockPayEvent::MintAdded {
+            accounting: *accounting_state.key,
+            mint: *token_mint.key,
+            vault: *vault_account.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_fund_rent_budget(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let accounting = next_account_info(account_info_iter)?;
+        if accounting.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+        if accounting_info.authority != *authority.key {
+            return Err(ClockPayError::WrongAuthority.into());
+        }
+
+        let rent_budget = next_account_info(account_info_iter)?;
+        let (rent_budget_pda, _) = Accounting::find_rent_budget_address(program_id, accounting.key);
+        if rent_budget_pda != *rent_budget.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let system_program = next_account_info(account_info_iter)?;
+
+        // The budget stays a plain system account so it can fund
+        // `create_associated_token_account_idempotent` as the payer.
+        let transfer_ix =
+            solana_program::system_instruction::transfer(authority.key, rent_budget.key, lamports);
+        invoke(
+            &transfer_ix,
+            &[
+                authority.clone(),
+                rent_budget.clone(),
+                system_program.clone(),
+            ],
+        )?;
+
+        ClockPayEvent::RentBudgetFunded {
+            accounting: *accounting.key,
+            amount: lamports,
+            balance: rent_budget.lamports(),
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_fund_fee_reserve(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let mut accounting_info = load_authorized_accounting(program_id, authority, accounting)?;
+
+        let transfer_ix =
+            solana_program::system_instruction::transfer(authority.key, accounting.key, lamports);
+        invoke(
+            &transfer_ix,
+            &[
+                authority.clone(),
+                accounting.clone(),
+                system_program.clone(),
+            ],
+        )?;
+
+        accounting_info.fee_reserve = accounting_info
+            .fee_reserve
+            .checked_add(lamports)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::FeeReserveFunded {
+            accounting: *accounting.key,
+            amount: lamports,
+            reserve: accounting_info.fee_reserve,
+        }
+        .emit();
+        report_fee_reserve(accounting.key, &accounting_info);
+        Ok(())
+    }
+
+    fn process_withdraw_fee_reserve(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let mut accounting_info = load_authorized_accounting(program_id, authority, accounting)?;
+
+        // The reserve is held on top of the accounting's own rent, so only
+        // lamports it accounts for can leave.
+        if lamports > accounting_info.fee_reserve {
+            return Err(ClockPayError::InsufficientFunds.into());
+        }
+        let remaining = accounting
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(ClockPayError::InsufficientFunds)?;
+        if remaining < Rent::get()?.minimum_balance(accounting.data_len()) {
+            return Err(ClockPayError::InsufficientFunds.into());
+        }
+        **accounting.try_borrow_mut_lamports()? = remaining;
+        **authority.try_borrow_mut_lamports()? = authority
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(ClockPayError::ArithmeticError)?;
+
+        accounting_info.fee_reserve -= lamports;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::FeeReserveWithdrawn {
+            accounting: *accounting.key,
+            amount: lamports,
+            reserve: accounting_info.fee_reserve,
+        }
+        .emit();
+        report_fee_reserve(accounting.key, &accounting_info);
+        Ok(())
+    }
+
+    fn process_withdraw_rent_budget(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        load_authorized_accounting(program_id, authority, accounting)?;
+
+        let rent_budget = next_account_info(account_info_iter)?;
+        let (rent_budget_pda, rent_budget_bump) =
+            Accounting::find_rent_budget_address(program_id, accounting.key);
+        if rent_budget_pda != *rent_budget.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let system_program = next_account_info(account_info_iter)?;
+
+        let remaining = rent_budget
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(ClockPayError::InsufficientFunds)?;
+        if remaining < Rent::get()?.minimum_balance(0) {
+            return Err(ClockPayError::InsufficientFunds.into());
+        }
+
+        let rent_budget_seeds = &[
+            b"rent_budget".as_ref(),
+            accounting.key.as_ref(),
+            &[rent_budget_bump],
+        ];
+        let transfer_ix =
+            solana_program::system_instruction::transfer(rent_budget.key, authority.key, lamports);
+        invoke_signed(
+            &transfer_ix,
+            &[
+                rent_budget.clone(),
+                authority.clone(),
+                system_program.clone(),
+            ],
+            &[&rent_budget_seeds[..]],
+        )?;
+
+        ClockPayEvent::RentBudgetWithdrawn {
+            accounting: *accounting.key,
+            amount: lamports,
+            balance: rent_budget.lamports(),
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_set_thread_min_balance(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let mut accounting_info = load_authorized_accounting(program_id, authority, accounting)?;
+
+        accounting_info.thread_min_balance = lamports;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::ThreadMinBalanceSet {
+            accounting: *accounting.key,
+            lamports,
+        }
+        .emit();
+        report_fee_reserve(accounting.key, &accounting_info);
+        Ok(())
+    }
+
+    fn process_fund_threads(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let accounting = next_account_info(account_info_iter)?;
+        if accounting.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let mut accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+
+        while let Some(payroll) = account_info_iter.next() {
+            let thread = next_account_info(account_info_iter)?;
+            if payroll.owner != program_id {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
+            if payroll_info.accounting != *accounting.key
+                || !payroll_info.active
+                || payroll_info.thread != *thread.key
+            {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+
+            let topped_up = top_up_thread(accounting, thread, &mut accounting_info)?;
+            report_thread_top_up(payroll.key, thread.key, topped_up, &accounting_info);
+        }
+
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+        report_fee_reserve(accounting.key, &accounting_info);
+        Ok(())
+    }
+
+    fn process_new_payroll_batch<O: PriceOracle>(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        args: NewPayrollBatchArgs,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        for payroll_args in args.payrolls {
+            Self::process_new_payroll::<O>(account_info_iter, program_id, payroll_args)?;
+            if args.start {
+                Self::process_init_payment(account_info_iter, program_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn process_set_payout_account(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let receiver = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payout_account = next_account_info(account_info_iter)?;
+        let thread = next_account_info(account_info_iter)?;
+        let thread_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        if !receiver.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if payroll.owner != program_id || accounting.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let mut payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
+        if payroll_info.receiver != *receiver.key {
+            return Err(ClockPayError::WrongAuthority.into());
+        }
+        if payroll_info.accounting != *accounting.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if token::is_native_mint(&payroll_info.mint) {
+            return Err(ClockPayError::NativePayout.into());
+        }
+        let accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+        let (mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+
+        let payout_info = token::unpack_token_account(payout_account, &mint_vault.token_program)?;
+        if payout_info.owner != payroll_info.receiver {
+            return Err(ClockPayError::WrongTokenAccountOwner.into());
+        }
+        if payout_info.mint != payroll_info.mint {
+            return Err(ClockPayError::WrongMint.into());
+        }
+        payroll_info.payout_account = *payout_account.key;
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+
+        // A started payroll's thread holds the `Pay` instruction with the
+        // old account, so it is rebuilt.
+        if payroll_info.thread != Pubkey::default() {
+            if *thread.key != payroll_info.thread {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let (history, _) = PaymentHistory::find_address(program_id, payroll.key);
+            let pay_ix = pay_instruction(
+                program_id,
+                payroll.key,
+                accounting.key,
+                &mint_vault.vault,
+                thread.key,
+                &mint_vault.token_program,
+                &history,
+                &payroll_info.mint,
+                record.map(|record| record.key),
+                &payroll_info,
+            );
+            let payroll_seeds = &[
+                b"payroll".as_ref(),
+                payroll_info.accounting.as_ref(),
+                payroll_info.receiver.as_ref(),
+                &[payroll_info.bump],
+            ];
+            msg!("Update payroll thread");
+            clockwork_sdk::cpi::thread_update(
+                CpiContext::new_with_signer(
+                    thread_program.clone(),
+                    clockwork_sdk::cpi::ThreadUpdate {
+                        authority: payroll.clone(),
+                        system_program: system_program.clone(),
+                        thread: thread.clone(),
+                    },
+                    &[&payroll_seeds[..]],
+                ),
+                ThreadSettings {
+                    fee: None,
+                    kickoff_instruction: Some(pay_ix.into()),
+                    rate_limit: None,
+                    trigger: None,
+                },
+            )?;
+        }
+
+        ClockPayEvent::PayoutAccountSet {
+            payroll: *payroll.key,
+            payout_account: *payout_account.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_set_transfer_approval(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        required: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let mut accounting_info = load_authorized_accounting(program_id, authority, accounting)?;
+
+        accounting_info.transfer_approval = required;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::TransferApprovalSet {
+            accounting: *accounting.key,
+            required,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_transfer_payroll(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let receiver = next_account_info(account_info_iter)?;
+        let new_receiver = next_account_info(account_info_iter)?;
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let new_payroll = next_account_info(account_info_iter)?;
+        let history = next_account_info(account_info_iter)?;
+        let new_history = next_account_info(account_info_iter)?;
+        let thread = next_account_info(account_info_iter)?;
+        let new_thread = next_account_info(account_info_iter)?;
+        let thread_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        if !receiver.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if payroll.owner != program_id || accounting.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let mut accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+        if accounting_info.authority != *authority.key {
+            return Err(ClockPayError::WrongAuthority.into());
+        }
+        if accounting_info.transfer_approval && !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let mut payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
+        if payroll_info.receiver != *receiver.key {
+            return Err(ClockPayError::WrongAuthority.into());
+        }
+        if payroll_info.accounting != *accounting.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if new_receiver.key == receiver.key {
+            return Err(ClockPayError::SameReceiver.into());
+        }
+        let (mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+
+        let (new_payroll_pda, new_bump) =
+            Payroll::find_address(program_id, accounting.key, new_receiver.key);
+        let (history_pda, _) = PaymentHistory::find_address(program_id, payroll.key);
+        let (new_history_pda, new_history_bump) =
+            PaymentHistory::find_address(program_id, new_payroll.key);
+        if *new_payroll.key != new_payroll_pda
+            || *history.key != history_pda
+            || *new_history.key != new_history_pda
+        {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        let payroll_seeds = &[
+            b"payroll".as_ref(),
+            payroll_info.accounting.as_ref(),
+            payroll_info.receiver.as_ref(),
+            &[payroll_info.bump],
+        ];
+        let new_payroll_seeds = &[
+            b"payroll".as_ref(),
+            accounting.key.as_ref(),
+            new_receiver.key.as_ref(),
+            &[new_bump],
+        ];
+
+        // The thread's id and authority are tied to the old receiver and
+        // payroll, so it is replaced. Its lamports, which the authority and
+        // the fee reserve paid, go back to the reserve.
+        let started = payroll_info.thread != Pubkey::default();
+        if started {
+            if *thread.key != payroll_info.thread {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let before = accounting.lamports();
+            msg!("Delete payroll thread");
+            clockwork_sdk::cpi::thread_delete(CpiContext::new_with_signer(
+                thread_program.clone(),
+                clockwork_sdk::cpi::ThreadDelete {
+                    authority: payroll.clone(),
+                    close_to: accounting.clone(),
+                    thread: thread.clone(),
+                },
+                &[&payroll_seeds[..]],
+            ))?;
+            accounting_info.fee_reserve = accounting_info
+                .fee_reserve
+                .checked_add(accounting.lamports().saturating_sub(before))
+                .ok_or(ClockPayError::ArithmeticError)?;
+        }
+
+        msg!("Move payroll account");
+        move_pda_account(
+            receiver,
+            payroll,
+            new_payroll,
+            system_program,
+            program_id,
+            &[&new_payroll_seeds[..]],
+        )?;
+        payroll_info.receiver = *new_receiver.key;
+        payroll_info.bump = new_bump;
+        payroll_info.payout_account = Pubkey::default();
+        payroll_info.thread = if started {
+            *new_thread.key
+        } else {
+            Pubkey::default()
+        };
+
+        if history.owner == program_id {
+            msg!("Move payment history account");
+            let new_history_seeds = &[
+                b"history".as_ref(),
+                new_payroll.key.as_ref(),
+                &[new_history_bump],
+            ];
+            move_pda_account(
+                receiver,
+                history,
+                new_history,
+                system_program,
+                program_id,
+                &[&new_history_seeds[..]],
+            )?;
+            let mut history_info = PaymentHistory::deserialize(
+                &mut &new_history.data.borrow()[..PaymentHistory::HEADER_SIZE],
+            )?;
+            history_info.payroll = *new_payroll.key;
+            history_info.receiver = *new_receiver.key;
+            history_info.bump = new_history_bump;
+            history_info.serialize(&mut &mut new_history.data.borrow_mut()[..])?;
+        }
+
+        if started {
+            let pay_ix = pay_instruction(
+                program_id,
+                new_payroll.key,
+                accounting.key,
+                &mint_vault.vault,
+                new_thread.key,
+                &mint_vault.token_program,
+                new_history.key,
+                &payroll_info.mint,
+                record.map(|record| record.key),
+                &payroll_info,
+            );
+            msg!("Create thread for pay_ix");
+            clockwork_sdk::cpi::thread_create(
+                CpiContext::new_with_signer(
+                    thread_program.clone(),
+                    clockwork_sdk::cpi::ThreadCreate {
+                        authority: new_payroll.clone(),
+                        payer: receiver.clone(),
+                        system_program: system_program.clone(),
+                        thread: new_thread.clone(),
+                    },
+                    &[&new_payroll_seeds[..]],
+                ),
+                Payroll::thread_id(new_receiver.key),
+                pay_ix.into(),
+                Trigger::Cron {
+                    schedule: payroll_info.schedule(),
+                    skippable: false,
+                },
+            )?;
+            if !payroll_info.active {
+                clockwork_sdk::cpi::thread_pause(CpiContext::new_with_signer(
+                    thread_program.clone(),
+                    clockwork_sdk::cpi::ThreadPause {
+                        authority: new_payroll.clone(),
+                        thread: new_thread.clone(),
+                    },
+                    &[&new_payroll_seeds[..]],
+                ))?;
+            }
+        }
+        payroll_info.serialize(&mut &mut new_payroll.data.borrow_mut()[..])?;
+
+        let topped_up = if started && payroll_info.active {
+            top_up_thread(accounting, new_thread, &mut accounting_info)?
+        } else {
+            0
+        };
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+        report_thread_top_up(new_payroll.key, new_thread.key, topped_up, &accounting_info);
+
+        ClockPayEvent::PayrollTransferred {
+            payroll: *payroll.key,
+            new_payroll: *new_payroll.key,
+            new_receiver: *new_receiver.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_set_advance_limit(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        max_cycles: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let mut accounting_info = load_authorized_accounting(program_id, authority, accounting)?;
+
+        accounting_info.max_advance_cycles = max_cycles;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::AdvanceLimitSet {
+            accounting: *accounting.key,
+            max_cycles,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_advance_request(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        args: AdvanceArgs,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let receiver = next_account_info(account_info_iter)?;
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let receiver_wallet = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let rent_budget = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        if !receiver.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if payroll_info.receiver != *receiver.key {
+            return Err(ClockPayError::WrongAuthority.into());
+        }
+        if payroll_info.mint != *token_mint.key {
+            return Err(ClockPayError::WrongMint.into());
+        }
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            token_mint.key,
+        )?;
+        if mint_vault.vault != *vault.key || mint_vault.token_program != *token_program.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if payroll_info.is_complete() || payroll_info.has_ended(Clock::get()?.unix_timestamp) {
+            return Err(ClockPayError::TriedExceedingPaymentLimit.into());
+        }
+
+        let limit = payroll_info
+            .advance_limit(mint_vault.decimals, accounting_info.max_advance_cycles)
+            .ok_or(ClockPayError::InvalidPrice)?;
+        let outstanding = payroll_info
+            .advance_outstanding
+            .checked_add(args.amount)
+            .ok_or(ClockPayError::ArithmeticError)?;
+        if args.amount == 0 || outstanding > limit {
+            return Err(ClockPayError::AdvanceLimitExceeded.into());
+        }
+
+        let vault_info = token::unpack_token_account(vault, token_program.key)?;
+        if vault_info.amount < args.amount {
+            return Err(ClockPayError::InsufficientFunds.into());
+        }
+        if accounting_info.funding_mode == FundingMode::Delegated
+            && (vault_info.delegate != COption::Some(*accounting.key)
+                || vault_info.delegated_amount < args.amount
+                || accounting_info.balance < args.amount)
+        {
+            return Err(ClockPayError::AllowanceExceeded.into());
+        }
+
+        let (rent_budget_pda, rent_budget_bump) =
+            Accounting::find_rent_budget_address(program_id, accounting.key);
+        if rent_budget_pda != *rent_budget.key {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        if *system_program.key != SystemProgramId {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let accounting_seeds = &[
+            b"accounting".as_ref(),
+            accounting_info.authority.as_ref(),
+            &[accounting_info.bump],
+        ];
+
+        let paid_to = if token::is_native_mint(token_mint.key) {
+            let (unwrap_pda, unwrap_bump) = Payroll::find_unwrap_address(program_id, payroll.key);
+            if unwrap_pda != *receiver_wallet.key {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let rent_budget_seeds = &[
+                b"rent_budget".as_ref(),
+                accounting.key.as_ref(),
+                &[rent_budget_bump],
+            ];
+            let unwrap_seeds = &[b"unwrap".as_ref(), payroll.key.as_ref(), &[unwrap_bump]];
+            unwrap_to_receiver(
+                token_program,
+                vault,
+                token_mint,
+                receiver_wallet,
+                receiver,
+                accounting,
+                rent_budget,
+                system_program,
+                &[
+                    &accounting_seeds[..],
+                    &rent_budget_seeds[..],
+                    &unwrap_seeds[..],
+                ],
+                args.amount,
+                mint_vault.decimals,
+            )?;
+            *receiver.key
+        } else {
+            if *receiver_wallet.key != payroll_info.payout_address(token_program.key) {
+                return Err(ClockPayError::InvalidAccount.into());
+            }
+            let receiver_wallet_info =
+                token::unpack_token_account(receiver_wallet, token_program.key)?;
+            if receiver_wallet_info.owner != payroll_info.receiver {
+                return Err(ClockPayError::WrongTokenAccountOwner.into());
+            }
+            if receiver_wallet_info.mint != payroll_info.mint {
+                return Err(ClockPayError::WrongMint.into());
+            }
+            transfer_from_vault(
+                token_program,
+                vault,
+                token_mint,
+                receiver_wallet,
+                accounting,
+                &accounting_seeds[..],
+                args.amount,
+                mint_vault.decimals,
+            )?;
+            *receiver_wallet.key
+        };
+
+        payroll_info.advance_outstanding = outstanding;
+        mint_vault.balance = vault_balance(
+            vault,
+            token_program.key,
+            accounting.key,
+            accounting_info.funding_mode,
+        )?;
+        recommit(&mut mint_vault, &mut payroll_info)?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+
+        ClockPayEvent::AdvancePaid {
+            payroll: *payroll.key,
+            receiver_wallet: paid_to,
+            amount: args.amount,
+            outstanding,
+        }
+        .emit();
+        report_solvency(accounting.key, &mint_vault);
+        Ok(())
+    }
+
+    fn process_add_adjustment(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        args: AdjustmentArgs,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+
+        let index = payroll_info.adjustment_count as usize;
+        if index >= Payroll::MAX_ADJUSTMENTS {
+            return Err(ClockPayError::TooManyAdjustments.into());
+        }
+        payroll_info.adjustments[index] = Adjustment {
+            amount: args.amount,
+            memo: args.memo,
+        };
+        payroll_info.adjustment_count += 1;
+        if payroll_info.adjustment_total().is_none() {
+            return Err(ClockPayError::ArithmeticError.into());
+        }
+
+        recommit(&mut mint_vault, &mut payroll_info)?;
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::AdjustmentAdded {
+            payroll: *payroll.key,
+            amount: args.amount,
+            memo: args.memo,
+        }
+        .emit();
+        report_solvency(accounting.key, &mint_vault);
+        Ok(())
+    }
+
+    fn process_reprice_payroll<O: PriceOracle>(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let accounting = next_account_info(account_info_iter)?;
+        let payroll = next_account_info(account_info_iter)?;
+        let (mut accounting_info, mut payroll_info) =
+            load_authorized_payroll(program_id, authority, accounting, payroll)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            &payroll_info.mint,
+        )?;
+        let price_feed = next_account_info(account_info_iter)?;
+        if !payroll_info.pricing.is_enabled() || *price_feed.key != payroll_info.pricing.price_feed
+        {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        // Only the slippage check is skipped: a stale or uncertain price, or
+        // one that can't convert the amount, is still refused.
+        let price = O::read_price(price_feed)?;
+        oracle::check_price(&payroll_info.pricing, &price, Clock::get()?.unix_timestamp)?;
+        oracle::convert(
+            payroll_info.amount,
+            payroll_info.pricing.quote_decimals,
+            price.price,
+            price.expo,
+            mint_vault.decimals,
+        )?;
+        payroll_info.pricing.last_price = price.price;
+        payroll_info.pricing.last_expo = price.expo;
+
+        recommit(&mut mint_vault, &mut payroll_info)?;
+        payroll_info.serialize(&mut &mut payroll.data.borrow_mut()[..])?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::PayrollRepriced {
+            payroll: *payroll.key,
+            price: price.price,
+            expo: price.expo,
+        }
+        .emit();
+        report_solvency(accounting.key, &mint_vault);
+        Ok(())
+    }
+
+    fn process_sync(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let accounting = next_account_info(account_info_iter)?;
+        if accounting.owner != program_id {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+        let mut accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+
+        let token_mint = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let (mut mint_vault, record) = next_mint_vault(
+            account_info_iter,
+            program_id,
+            accounting.key,
+            &accounting_info,
+            token_mint.key,
+        )?;
+        if *vault.key != mint_vault.vault {
+            return Err(ClockPayError::InvalidAccount.into());
+        }
+
+        let previous = mint_vault.balance;
+        mint_vault.balance = vault_balance(
+            vault,
+            &mint_vault.token_program,
+            accounting.key,
+            accounting_info.funding_mode,
+        )?;
+        store_mint_vault(&mint_vault, record, &mut accounting_info)?;
+        accounting_info.serialize(&mut &mut accounting.data.borrow_mut()[..])?;
+
+        ClockPayEvent::BalanceSynced {
+            accounting: *accounting.key,
+            mint: mint_vault.mint,
+            previous,
+            balance: mint_vault.balance,
+        }
+        .emit();
+        Ok(())
+    }
+}
+
+/// Creates a rent-exempt pda of `size` bytes owned by `owner`, funded by
+/// `payer`. Lamports someone already sent to the address are kept and only
+/// the shortfall is transferred, as `create_account` would fail on them and
+/// leave the address unusable.
+fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    owner: &Pubkey,
+    size: usize,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    if *account.owner != SystemProgramId || !account.data_is_empty() {
+        return Err(ClockPayError::AccountAlreadyInitialized.into());
+    }
+    let lamports = Rent::get()?.minimum_balance(size);
+
+    if account.lamports() == 0 {
+        let create_account_ix = solana_program::system_instruction::create_account(
+            payer.key,
+            account.key,
+            lamports,
+            size as u64,
+            owner,
+        );
+        return invoke_signed(
+            &create_account_ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
+            signer_seeds,
+        );
+    }
+
+    let shortfall = lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        let transfer_ix =
+            solana_program::system_instruction::transfer(payer.key, account.key, shortfall);
+        invoke_signed(
+            &transfer_ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
+            signer_seeds,
+        )?;
+    }
+    let allocate_ix = solana_program::system_instruction::allocate(account.key, size as u64);
+    invoke_signed(
+        &allocate_ix,
+        &[account.clone(), system_program.clone()],
+        signer_seeds,
+    )?;
+    let assign_ix = solana_program::system_instruction::assign(account.key, owner);
+    invoke_signed(
+        &assign_ix,
+        &[account.clone(), system_program.clone()],
+        signer_seeds,
+    )
+}
+
+/// Recreates the program-owned `from` at the pda `to` with the same data and
+/// lamports, and closes `from`. `payer` only covers a rise in rent.
+fn move_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    owner: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(from.lamports())
+        .ok_or(ClockPayError::ArithmeticError)?;
+    **from.try_borrow_mut_lamports()? = 0;
+    create_pda_account(
+        payer,
+        to,
+        system_program,
+        owner,
+        from.data_len(),
+        signer_seeds,
+    )?;
+    to.data.borrow_mut().copy_from_slice(&from.data.borrow());
+    from.data.borrow_mut().fill(0);
+    Ok(())
+}
+
+/// Creates the accounting's associated token account for `mint` as its
+/// vault, or checks the one that already exists, e.g. because tokens were
+/// sent to the address before the vault was added. Returns the tokens the
+/// vault holds.
+fn create_vault<'a>(
+    payer: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    accounting: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    atoken_program: &AccountInfo<'a>,
+) -> Result<u64, ProgramError> {
+    let create_vault_ix = create_associated_token_account_idempotent(
+        payer.key,
+        accounting.key,
+        mint.key,
+        token_program.key,
+    );
+    invoke(
+        &create_vault_ix,
+        &[
+            payer.clone(),
+            vault.clone(),
+            accounting.clone(),
+            mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            atoken_program.clone(),
+        ],
+    )?;
+
+    let vault_info = token::unpack_token_account(vault, token_program.key)?;
+    if vault_info.mint != *mint.key {
+        return Err(ClockPayError::WrongMint.into());
+    }
+    if vault_info.owner != *accounting.key {
+        return Err(ClockPayError::WrongTokenAccountOwner.into());
+    }
+    Ok(vault_info.amount)
+}
+
+/// Resolves the vault that holds `mint` for an accounting. The primary mint's
+/// is kept in the accounting itself; any other mint's `MintVault` record is
+/// read as the next account.
+fn next_mint_vault<'a, 'b, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    account_info_iter: &mut I,
+    program_id: &Pubkey,
+    accounting: &Pubkey,
+    accounting_info: &Accounting,
+    mint: &Pubkey,
+) -> Result<(MintVault, Option<&'a AccountInfo<'b>>), ProgramError> {
+    if *mint == accounting_info.mint {
+        return Ok((accounting_info.primary_vault(accounting), None));
+    }
+
+    let record = next_account_info(account_info_iter)?;
+    if record.owner != program_id {
+        return Err(ClockPayError::UnknownMint.into());
+    }
+    let mint_vault = MintVault::try_from_slice(&record.data.borrow())?;
+    if mint_vault.accounting != *accounting || mint_vault.mint != *mint {
+        return Err(ClockPayError::UnknownMint.into());
+    }
+    Ok((mint_vault, Some(record)))
+}
+
+/// The balance a vault really has available for payments: its token amount,
+/// or in `Delegated` mode the allowance the treasury still approves the
+/// accounting for.
+fn vault_balance(
+    vault: &AccountInfo,
+    token_program: &Pubkey,
+    accounting: &Pubkey,
+    funding_mode: FundingMode,
+) -> Result<u64, ProgramError> {
+    let vault_info = token::unpack_token_account(vault, token_program)?;
+    Ok(match funding_mode {
+        FundingMode::Custodial => vault_info.amount,
+        FundingMode::Delegated if vault_info.delegate == COption::Some(*accounting) => {
+            vault_info.delegated_amount
+        }
+        FundingMode::Delegated => 0,
+    })
+}
+
+/// Writes a vault's balance and commitment back to where `next_mint_vault`
+/// loaded it from. The accounting itself still has to be serialized by the
+/// caller.
+fn store_mint_vault(
+    mint_vault: &MintVault,
+    record: Option<&AccountInfo>,
+    accounting_info: &mut Accounting,
+) -> ProgramResult {
+    match record {
+        Some(record) => mint_vault.serialize(&mut &mut record.data.borrow_mut()[..])?,
+        None => {
+            accounting_info.balance = mint_vault.balance;
+            accounting_info.committed_amount = mint_vault.committed_amount;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces what a payroll counted toward its vault's `committed_amount`
+/// with what it owes now, after a payment or a change to its terms.
+fn recommit(mint_vault: &mut MintVault, payroll_info: &mut Payroll) -> ProgramResult {
+    let now = Clock::get()?.unix_timestamp;
+    let committed = payroll_info.commitment(mint_vault.decimals, now);
+    mint_vault.committed_amount = mint_vault
+        .committed_amount
+        .saturating_sub(payroll_info.committed)
+        .checked_add(committed)
+        .ok_or(ClockPayError::ArithmeticError)?;
+    payroll_info.committed = committed;
+    Ok(())
+}
+
+/// Marks a payroll with nothing left to pay inactive and releases what it
+/// still counted toward its vault's `committed_amount`.
+fn settle_payroll(
+    accounting_info: &mut Accounting,
+    payroll_info: &mut Payroll,
+    mint_vault: &mut MintVault,
+) -> ProgramResult {
+    accounting_info.active_payrolls = accounting_info
+        .active_payrolls
+        .checked_sub(1)
+        .ok_or(ClockPayError::ArithmeticError)?;
+    payroll_info.active = false;
+    mint_vault.committed_amount = mint_vault
+        .committed_amount
+        .saturating_sub(payroll_info.committed);
+    payroll_info.committed = 0;
+    Ok(())
+}
+
+/// Emits `Overcommitted` when a vault's balance no longer covers what its
+/// payrolls still owe.
+fn report_solvency(accounting: &Pubkey, mint_vault: &MintVault) {
+    if !mint_vault.is_solvent() {
+        ClockPayEvent::Overcommitted {
+            accounting: *accounting,
+            mint: mint_vault.mint,
+            balance: mint_vault.balance,
+            committed: mint_vault.committed_amount,
+        }
+        .emit();
+    }
+}
+
+/// Approves the accounting pda to transfer up to `allowance` out of `treasury`.
+fn approve_delegate<'a>(
+    token_program: &AccountInfo<'a>,
+    treasury: &AccountInfo<'a>,
+    accounting: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    allowance: u64,
+) -> ProgramResult {
+    let approve_ix = spl_token_2022::instruction::approve(
+        token_program.key,
+        treasury.key,
+        accounting.key,
+        owner.key,
+        &[owner.key],
+        allowance,
+    )?;
+    invoke(
+        &approve_ix,
+        &[
+            treasury.clone(),
+            accounting.clone(),
+            owner.clone(),
+            token_program.clone(),
+        ],
+    )
+}
+
+/// Loads the accounting for an instruction signed by its authority.
+fn load_authorized_accounting(
+    program_id: &Pubkey,
+    authority: &AccountInfo,
+    accounting: &AccountInfo,
+) -> Result<Accounting, ProgramError> {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if accounting.owner != program_id {
+        return Err(ClockPayError::InvalidAccount.into());
+    }
+    let accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+    if accounting_info.authority != *authority.key {
+        return Err(ClockPayError::WrongAuthority.into());
+    }
+    Ok(accounting_info)
+}
+
+/// Moves lamports from the accounting's fee reserve into `thread` until it
+/// holds `thread_min_balance`, as far as the reserve allows. Clockwork threads
+/// are funded by plain lamport transfers. Returns the lamports moved.
+fn top_up_thread(
+    accounting: &AccountInfo,
+    thread: &AccountInfo,
+    accounting_info: &mut Accounting,
+) -> Result<u64, ProgramError> {
+    let amount = accounting_info
+        .thread_min_balance
+        .saturating_sub(thread.lamports())
+        .min(accounting_info.fee_reserve);
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    **accounting.try_borrow_mut_lamports()? = accounting
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(ClockPayError::InsufficientFunds)?;
+    **thread.try_borrow_mut_lamports()? = thread
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ClockPayError::ArithmeticError)?;
+    accounting_info.fee_reserve -= amount;
+    Ok(amount)
+}
+
+fn report_thread_top_up(
+    payroll: &Pubkey,
+    thread: &Pubkey,
+    amount: u64,
+    accounting_info: &Accounting,
+) {
+    if amount > 0 {
+        ClockPayEvent::ThreadToppedUp {
+            payroll: *payroll,
+            thread: *thread,
+            amount,
+            reserve: accounting_info.fee_reserve,
+        }
+        .emit();
+    }
+}
+
+/// Emits `FeeReserveLow` when the reserve can't cover the accounting's threads.
+fn report_fee_reserve(accounting: &Pubkey, accounting_info: &Accounting) {
+    if accounting_info.fee_reserve_low() {
+        msg!("Fee reserve is low");
+        ClockPayEvent::FeeReserveLow {
+            accounting: *accounting,
+            reserve: accounting_info.fee_reserve,
+            required: accounting_info
+                .thread_min_balance
+                .saturating_mul(accounting_info.active_payrolls),
+        }
+        .emit();
+    }
+}
+
+/// Loads the accounting and payroll for an instruction signed by the
+/// accounting's authority, checking that all three belong together.
+fn load_authorized_payroll(
+    program_id: &Pubkey,
+    authority: &AccountInfo,
+    accounting: &AccountInfo,
+    payroll: &AccountInfo,
+) -> Result<(Accounting, Payroll), ProgramError> {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if accounting.owner != program_id || payroll.owner != program_id {
+        return Err(ClockPayError::InvalidAccount.into());
+    }
+
+    let accounting_info = Accounting::try_from_slice(&accounting.data.borrow())?;
+    if accounting_info.authority != *authority.key {
+        return Err(ClockPayError::WrongAuthority.into());
+    }
+    let payroll_info = Payroll::try_from_slice(&payroll.data.borrow())?;
+    if payroll_info.accounting != *accounting.key {
+        return Err(ClockPayError::InvalidAccount.into());
+    }
+
+    Ok((accounting_info, payroll_info))
+}
+
+/// Closes a program-owned account, sending its lamports to `destination`.
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(account.lamports())
+        .ok_or(ClockPayError::ArithmeticError)?;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.data.borrow_mut().fill(0);
+    Ok(())
+}
+
+/// Builds the `Pay` instruction a payroll's thread executes every cycle.
+#[allow(clippy::too_many_arguments)]
+fn pay_instruction(
+    program_id: &Pubkey,
+    payroll: &Pubkey,
+    accounting: &Pubkey,
+    vault: &Pubkey,
+    thread: &Pubkey,
+    token_program: &Pubkey,
+    history: &Pubkey,
+    mint: &Pubkey,
+    mint_vault: Option<&Pubkey>,
+    payroll_info: &Payroll,
+) -> Instruction {
+    let receiver_wallet = if token::is_native_mint(mint) {
+        Payroll::find_unwrap_address(program_id, payroll).0
+    } else {
+        payroll_info.payout_address(token_program)
+    };
+    let (rent_budget, _) = Accounting::find_rent_budget_address(program_id, accounting);
+    let mut accounts = vec![
+        AccountMeta::new(*payroll, false),
+        AccountMeta::new(*accounting, false),
+        AccountMeta::new(*vault, false),
+        AccountMeta::new(receiver_wallet, false),
+        AccountMeta::new(*thread, true),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(*history, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(payroll_info.receiver, false),
+        AccountMeta::new(rent_budget, false),
+        AccountMeta::new_readonly(SystemProgramId, false),
+        AccountMeta::new_readonly(AssociatedTokenProgramId, false),
+    ];
+    if let Some(mint_vault) = mint_vault {
+        accounts.push(AccountMeta::new(*mint_vault, false));
+    }
+    if payroll_info.pricing.is_enabled() {
+        accounts.push(AccountMeta::new_readonly(
+            payroll_info.pricing.price_feed,
+            false,
+        ));
+    }
+    for deduction in payroll_info.active_deductions() {
+        accounts.push(AccountMeta::new(deduction.destination, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: instruction::pay_data(),
+    }
+}
+
+/// Pays `amount` of wrapped SOL out of the vault as lamports to `receiver`.
+/// The tokens move into a temporary account that is closed into the rent
+/// budget, which then forwards `amount` and keeps back the account's rent.
+/// `signer_seeds` are those of the accounting, the rent budget and the
+/// temporary account.
+#[allow(clippy::too_many_arguments)]
+fn unwrap_to_receiver<'a>(
+    token_program: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    temporary: &AccountInfo<'a>,
+    receiver: &AccountInfo<'a>,
+    accounting: &AccountInfo<'a>,
+    rent_budget: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+    decimals: u8,
+) -> ProgramResult {
+    create_pda_account(
+        rent_budget,
+        temporary,
+        system_program,
+        token_program.key,
+        spl_token::state::Account::LEN,
+        signer_seeds,
+    )?;
+    let initialize_ix = spl_token_2022::instruction::initialize_account3(
+        token_program.key,
+        temporary.key,
+        mint.key,
+        accounting.key,
+    )?;
+    invoke(
+        &initialize_ix,
+        &[temporary.clone(), mint.clone(), token_program.clone()],
+    )?;
+
+    token::transfer_checked(
+        token_program,
+        vault,
+        mint,
+        temporary,
+        accounting,
+        signer_seeds,
+        amount,
+        decimals,
+    )?;
+    let close_ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        temporary.key,
+        rent_budget.key,
+        accounting.key,
+        &[],
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            temporary.clone(),
+            rent_budget.clone(),
+            accounting.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    let transfer_ix =
+        solana_program::system_instruction::transfer(rent_budget.key, receiver.key, amount);
+    invoke_signed(
+        &transfer_ix,
+        &[
+            rent_budget.clone(),
+            receiver.clone(),
+            system_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// Transfers `amount` out of the accounting vault, signing as the accounting pda.
+/// Returns the transfer fee withheld from what `destination` receives.
+#[allow(clippy::too_many_arguments)]
+fn transfer_from_vault<'a>(
+    token_program: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    accounting: &AccountInfo<'a>,
+    accounting_seeds: &[&[u8]],
+    amount: u64,
+    decimals: u8,
+) -> Result<u64, ProgramError> {
+    token::transfer_checked(
+        token_program,
+        vault,
+        mint,
+        destination,
+        accounting,
+        &[accounting_seeds],
+        amount,
+        decimals,
+    )?;
+    token::transfer_fee(mint, amount)
 }
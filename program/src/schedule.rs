@@ -0,0 +1,200 @@
+//! Fire times of the cron schedules payroll threads run on, computed with
+//! the same cron implementation as the Clockwork thread program.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use clockwork_cron::Schedule;
+use std::str::FromStr;
+
+/// How far back `previous_fire` searches, in seconds. A schedule that fires
+/// less often than this is treated as having no previous fire time.
+const MAX_LOOKBACK: i64 = 4 * 366 * 24 * 60 * 60;
+
+pub fn parse(schedule: &str) -> Option<Schedule> {
+    Schedule::from_str(schedule).ok()
+}
+
+/// The first time `schedule` fires strictly after `after`.
+pub fn next_fire(schedule: &Schedule, after: i64) -> Option<i64> {
+    let after = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp_opt(after, 0)?, Utc);
+    schedule.next_after(&after).map(|fire| fire.timestamp())
+}
+
+/// The last time `schedule` fired strictly before `before`.
+///
+/// Cron schedules can only be walked forwards, so this looks for a fire time
+/// in ever wider windows ending at `before`, starting from the gap to the
+/// next one, then bisects the window for the last second whose next fire is
+/// still before `before`. Both take a bounded number of steps, however often
+/// the schedule fires.
+pub fn previous_fire(schedule: &Schedule, before: i64) -> Option<i64> {
+    let fires_before = |after| matches!(next_fire(schedule, after), Some(fire) if fire < before);
+
+    let mut window = next_fire(schedule, before).map_or(1, |next| next - before);
+    let mut low = loop {
+        if window > MAX_LOOKBACK {
+            return None;
+        }
+        let start = before.checked_sub(window + 1)?;
+        if fires_before(start) {
+            break start;
+        }
+        window *= 2;
+    };
+    let mut high = before - 1;
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if fires_before(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    next_fire(schedule, low)
+}
+
+/// The last time `schedule` fired at or before `at`. `known` is a fire time
+/// at or before `at`, such as the one a payroll was last paid at, or 0; when
+/// no fire falls between the two it is the answer, and `previous_fire`'s
+/// search is skipped.
+pub fn last_fire(schedule: &Schedule, known: i64, at: i64) -> Option<i64> {
+    if known != 0 && known <= at && next_fire(schedule, known).map_or(true, |next| next > at) {
+        return Some(known);
+    }
+    previous_fire(schedule, at.checked_add(1)?)
+}
+
+/// The pay period a cycle covers: from the schedule's previous fire time up
+/// to the one the cycle was paid at.
+#[derive(Clone, Copy, Debug)]
+pub struct Period {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Period {
+    /// The period paid by a cycle running at `now`, which may run late.
+    pub fn current(schedule: &Schedule, now: i64) -> Option<Self> {
+        let end = previous_fire(schedule, now.checked_add(1)?)?;
+        let start = previous_fire(schedule, end)?;
+        Some(Self { start, end })
+    }
+
+    /// Like `current`, for a payroll whose last cycle paid the period ending
+    /// at `last_paid`, or 0 if none did. A cycle running before the fire
+    /// after next, as an on-time thread's does, pays the period right after
+    /// it without `previous_fire`'s search.
+    pub fn after(schedule: &Schedule, last_paid: i64, now: i64) -> Option<Self> {
+        if last_paid != 0 {
+            let end = next_fire(schedule, last_paid)?;
+            if end <= now && next_fire(schedule, end).map_or(true, |next| next > now) {
+                return Some(Self {
+                    start: last_paid,
+                    end,
+                });
+            }
+        }
+        Self::current(schedule, now)
+    }
+
+    pub fn length(&self) -> i64 {
+        self.end - self.start
+    }
+
+    /// Seconds of the period from `start_ts` up to `end_ts`, where an
+    /// `end_ts` of 0 means none.
+    pub fn overlap(&self, start_ts: i64, end_ts: i64) -> i64 {
+        let end = if end_ts == 0 {
+            self.end
+        } else {
+            self.end.min(end_ts)
+        };
+        (end - self.start.max(start_ts)).max(0)
+    }
+}
+
+/// `amount` scaled down to `worked` seconds out of a `length` second period.
+pub fn prorate(amount: u64, worked: i64, length: i64) -> Option<u64> {
+    if worked < 0 || length <= 0 {
+        return None;
+    }
+    ((amount as u128) * (worked as u128) / (length as u128))
+        .try_into()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+    /// Midnight UTC, 2023-11-15.
+    const MIDNIGHT: i64 = 1_700_006_400;
+
+    fn daily() -> Schedule {
+        parse("0 0 0 * * * *").unwrap()
+    }
+
+    #[test]
+    fn previous_fire_is_strictly_before() {
+        let schedule = daily();
+        assert_eq!(previous_fire(&schedule, MIDNIGHT), Some(MIDNIGHT - DAY));
+        assert_eq!(previous_fire(&schedule, MIDNIGHT + 1), Some(MIDNIGHT));
+        assert_eq!(previous_fire(&schedule, MIDNIGHT + DAY / 2), Some(MIDNIGHT));
+    }
+
+    #[test]
+    fn previous_fire_of_monthly_schedule() {
+        let schedule = parse("0 0 9 1 * * *").unwrap();
+        // 2023-03-01 09:00 and 2023-02-01 09:00 UTC.
+        assert_eq!(previous_fire(&schedule, 1_677_661_200), Some(1_675_242_000));
+    }
+
+    #[test]
+    fn previous_fire_gives_up_past_lookback() {
+        let schedule = parse("0 0 0 1 1 * 2000").unwrap();
+        assert_eq!(previous_fire(&schedule, MIDNIGHT), None);
+    }
+
+    #[test]
+    fn last_fire_trusts_a_known_fire_only_until_the_next() {
+        let schedule = daily();
+        assert_eq!(
+            last_fire(&schedule, MIDNIGHT, MIDNIGHT + 60),
+            Some(MIDNIGHT)
+        );
+        assert_eq!(
+            last_fire(&schedule, MIDNIGHT, MIDNIGHT + DAY + 60),
+            Some(MIDNIGHT + DAY)
+        );
+        assert_eq!(last_fire(&schedule, 0, MIDNIGHT + 60), Some(MIDNIGHT));
+    }
+
+    #[test]
+    fn current_period_is_the_one_just_paid() {
+        let period = Period::current(&daily(), MIDNIGHT + 60).unwrap();
+        assert_eq!((period.start, period.end), (MIDNIGHT - DAY, MIDNIGHT));
+        assert_eq!(period.overlap(MIDNIGHT - DAY / 2, 0), DAY / 2);
+    }
+
+    #[test]
+    fn period_after_the_last_paid_one() {
+        let schedule = daily();
+        let period = Period::after(&schedule, MIDNIGHT - DAY, MIDNIGHT + 60).unwrap();
+        assert_eq!((period.start, period.end), (MIDNIGHT - DAY, MIDNIGHT));
+
+        // A thread that fell behind pays the period just ended, as `current`.
+        let period = Period::after(&schedule, MIDNIGHT - 3 * DAY, MIDNIGHT + 60).unwrap();
+        assert_eq!((period.start, period.end), (MIDNIGHT - DAY, MIDNIGHT));
+        let period = Period::after(&schedule, 0, MIDNIGHT + 60).unwrap();
+        assert_eq!((period.start, period.end), (MIDNIGHT - DAY, MIDNIGHT));
+    }
+
+    #[test]
+    fn prorate_scales_down() {
+        assert_eq!(prorate(100, 1, 4), Some(25));
+        assert_eq!(prorate(100, 4, 4), Some(100));
+        assert_eq!(prorate(u64::MAX, 1, 1), Some(u64::MAX));
+        assert_eq!(prorate(100, -1, 4), None);
+        assert_eq!(prorate(100, 1, 0), None);
+    }
+}
@@ -1,19 +1,118 @@
+use crate::schedule;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct Accounting {
     pub authority: Pubkey,
     pub mint: Pubkey,
     pub active_payrolls: u64,
+    /// The token account payments are drawn from: the accounting's own
+    /// associated token account, or the authority's treasury when delegated.
     pub vault: Pubkey,
+    /// Tokens available for payments. In `Delegated` mode this is the
+    /// remaining allowance approved to the accounting.
     pub balance: u64,
     pub active: bool,
     pub bump: u8,
+    pub funding_mode: FundingMode,
+    /// The token program that owns `mint`: SPL Token or Token-2022.
+    pub token_program: Pubkey,
+    /// Decimals of `mint`, which every transfer is checked against.
+    pub decimals: u8,
+    /// Lamports held by the accounting, on top of its rent, for topping up
+    /// payroll threads so they can keep paying Clockwork execution fees.
+    pub fee_reserve: u64,
+    /// Balance every payroll thread is topped up to from `fee_reserve`.
+    /// Zero disables top-ups.
+    pub thread_min_balance: u64,
+    /// Tokens of `mint` still owed by payrolls that haven't been cancelled.
+    /// See `Payroll::committed`.
+    pub committed_amount: u64,
+    /// Whether moving a payroll to a new receiver with `TransferPayroll`
+    /// needs the authority's signature as well as the receiver's.
+    pub transfer_approval: bool,
+    /// Cycles' worth of pay a receiver may have advanced at once with
+    /// `AdvanceRequest`. Zero disables advances.
+    pub max_advance_cycles: u64,
 }
 
 impl Accounting {
-    pub const SIZE: usize = 32 + 32 + 8 + 32 + 8 + 1 + 1;
+    pub const SIZE: usize = 32 + 32 + 8 + 32 + 8 + 1 + 1 + 1 + 32 + 1 + 8 + 8 + 8 + 1 + 8;
+
+    pub fn find_address(program_id: &Pubkey, authority: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"accounting".as_ref(), authority.as_ref()], program_id)
+    }
+
+    /// The system-owned pda holding the lamports `Pay` spends creating
+    /// receivers' token accounts. Seeds [b"rent_budget".as_ref(), accounting.key.as_ref()].
+    pub fn find_rent_budget_address(program_id: &Pubkey, accounting: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"rent_budget".as_ref(), accounting.as_ref()], program_id)
+    }
+
+    /// Whether `fee_reserve` can no longer top every active payroll's thread
+    /// up from empty to `thread_min_balance`.
+    pub fn fee_reserve_low(&self) -> bool {
+        (self.fee_reserve as u128) < self.thread_min_balance as u128 * self.active_payrolls as u128
+    }
+
+    /// The primary mint's vault, which is kept in the accounting itself, in
+    /// the shape of a `MintVault` record.
+    pub fn primary_vault(&self, accounting: &Pubkey) -> MintVault {
+        MintVault {
+            accounting: *accounting,
+            mint: self.mint,
+            token_program: self.token_program,
+            decimals: self.decimals,
+            vault: self.vault,
+            balance: self.balance,
+            bump: self.bump,
+            committed_amount: self.committed_amount,
+        }
+    }
+}
+
+/// Vault for a mint an accounting pays in besides the primary `mint` it was
+/// initialized with. A pda with seeds [b"vault".as_ref(), accounting.key.as_ref(), mint.key.as_ref()].
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct MintVault {
+    pub accounting: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    /// The accounting pda's associated token account for `mint`.
+    pub vault: Pubkey,
+    pub balance: u64,
+    pub bump: u8,
+    /// Tokens of `mint` still owed by payrolls that haven't been cancelled.
+    pub committed_amount: u64,
+}
+
+impl MintVault {
+    pub const SIZE: usize = 32 + 32 + 32 + 1 + 32 + 8 + 1 + 8;
+
+    /// Whether `balance` covers everything committed to payrolls.
+    pub fn is_solvent(&self) -> bool {
+        self.balance >= self.committed_amount
+    }
+
+    pub fn find_address(program_id: &Pubkey, accounting: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"vault".as_ref(), accounting.as_ref(), mint.as_ref()],
+            program_id,
+        )
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FundingMode {
+    /// Payments are drawn from a vault owned by the accounting pda.
+    #[default]
+    Custodial,
+    /// Payments are pulled from the authority's treasury token account, on
+    /// which the accounting pda is an approved SPL delegate.
+    Delegated,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -22,15 +121,537 @@ pub struct Payroll {
     pub active: bool,
     pub amount: u64,
     pub total_amount_disbursed: u64,
+    pub deduction_count: u8,
+    pub deductions: [Deduction; Payroll::MAX_DEDUCTIONS],
     pub cron_schedule: [u8; 30],
     pub receiver: Pubkey,
+    /// Number of cycles to pay, or `OPEN_ENDED` to pay until cancelled.
     pub max_cycles: u64,
     pub cycles_completed: u64,
     pub thread: Pubkey,
     pub bump: u8,
+    /// The mint the payroll pays in: the accounting's primary mint, or one
+    /// with a `MintVault` record.
+    pub mint: Pubkey,
+    /// Set when `amount` is in a quote currency rather than in tokens.
+    pub pricing: QuotePricing,
+    /// Tokens this payroll still owes over its remaining cycles, as counted
+    /// in its vault's `committed_amount`. Quote payrolls are valued at the
+    /// last price they were paid at.
+    pub committed: u64,
+    /// Unix timestamp before which cycles pay nothing, or 0 to pay from the
+    /// first cycle.
+    pub start_ts: i64,
+    /// Unix timestamp from which the payroll stops paying, or 0 for none.
+    pub end_ts: i64,
+    /// Pay the cycles `start_ts` and `end_ts` fall within in proportion to
+    /// the part of their period they cover.
+    pub prorate: bool,
+    /// Token account of the receiver's chosen with `SetPayoutAccount`, or
+    /// the default pubkey to pay their associated token account.
+    pub payout_account: Pubkey,
+    /// Tokens advanced to the receiver that its next payments still have to
+    /// repay. Advances count toward `total_amount_disbursed` as they are
+    /// repaid.
+    pub advance_outstanding: u64,
+    pub adjustment_count: u8,
+    /// One-off changes to the next payment queued with `AddAdjustment`.
+    pub adjustments: [Adjustment; Payroll::MAX_ADJUSTMENTS],
+    /// Fire time ending the period the last cycle of a prorated payroll
+    /// paid, or 0 before its thread starts. Saves `Pay` searching backwards
+    /// through the schedule for it.
+    pub last_fire_ts: i64,
 }
 
 impl Payroll {
     pub const SCHEDULE_LEN: usize = 30;
-    pub const SIZE: usize = 32 + 1 + 8 + 8 + (30) + 32 + 8 + 8 + 32 + 1;
+    /// `max_cycles` of a payroll that has no cycle limit.
+    pub const OPEN_ENDED: u64 = u64::MAX;
+    pub const MAX_DEDUCTIONS: usize = 4;
+    pub const MAX_ADJUSTMENTS: usize = 4;
+    /// Most fires `cycles_before_end` walks through.
+    pub const MAX_COUNTED_CYCLES: u64 = 16;
+    pub const SIZE: usize = 32
+        + 1
+        + 8
+        + 8
+        + 1
+        + (Deduction::SIZE * Self::MAX_DEDUCTIONS)
+        + (30)
+        + 32
+        + 8
+        + 8
+        + 32
+        + 1
+        + 32
+        + QuotePricing::SIZE
+        + 8
+        + 8
+        + 8
+        + 1
+        + 32
+        + 8
+        + 1
+        + (Adjustment::SIZE * Self::MAX_ADJUSTMENTS)
+        + 8;
+
+    pub fn find_address(
+        program_id: &Pubkey,
+        accounting: &Pubkey,
+        receiver: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"payroll".as_ref(), accounting.as_ref(), receiver.as_ref()],
+            program_id,
+        )
+    }
+
+    /// The temporary token account a native SOL payment is unwrapped
+    /// through. Seeds [b"unwrap".as_ref(), payroll.key.as_ref()].
+    pub fn find_unwrap_address(program_id: &Pubkey, payroll: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"unwrap".as_ref(), payroll.as_ref()], program_id)
+    }
+
+    /// Encodes a cron schedule into the fixed-size `cron_schedule` field.
+    pub fn pack_schedule(schedule: &str) -> Option<[u8; Self::SCHEDULE_LEN]> {
+        let bytes = schedule.as_bytes();
+        if bytes.len() > Self::SCHEDULE_LEN {
+            return None;
+        }
+        let mut packed = [0; Self::SCHEDULE_LEN];
+        packed[..bytes.len()].copy_from_slice(bytes);
+        Some(packed)
+    }
+
+    /// The cron schedule as a string.
+    pub fn schedule(&self) -> String {
+        let bytes: Vec<u8> = self.cron_schedule.into_iter().filter(|&x| x != 0).collect();
+        String::from_utf8_lossy(&bytes).trim_end().to_string()
+    }
+
+    /// Id of the clockwork thread that pays `receiver`.
+    pub fn thread_id(receiver: &Pubkey) -> String {
+        receiver.to_string()[0..10].to_string()
+    }
+
+    /// The deductions configured for this payroll, in the order their
+    /// destinations are passed to `Pay`.
+    pub fn active_deductions(&self) -> &[Deduction] {
+        &self.deductions[..self.deduction_count as usize]
+    }
+
+    /// The adjustments queued for the next payment.
+    pub fn pending_adjustments(&self) -> &[Adjustment] {
+        &self.adjustments[..self.adjustment_count as usize]
+    }
+
+    /// What the queued adjustments add to the next payment in all, or
+    /// `None` if that overflows.
+    pub fn adjustment_total(&self) -> Option<i64> {
+        self.pending_adjustments()
+            .iter()
+            .try_fold(0i64, |total, adjustment| {
+                total.checked_add(adjustment.amount)
+            })
+    }
+
+    pub fn is_open_ended(&self) -> bool {
+        self.max_cycles == Self::OPEN_ENDED
+    }
+
+    /// Whether every cycle has been paid. Never true of an open-ended payroll.
+    pub fn is_complete(&self) -> bool {
+        !self.is_open_ended() && self.cycles_completed >= self.max_cycles
+    }
+
+    /// The token account payments go to. Native SOL payrolls instead pay the
+    /// receiver through their unwrap pda.
+    pub fn payout_address(&self, token_program: &Pubkey) -> Pubkey {
+        if self.payout_account != Pubkey::default() {
+            return self.payout_account;
+        }
+        get_associated_token_address_with_program_id(&self.receiver, &self.mint, token_program)
+    }
+
+    /// Whether the payroll's start date, if any, has been reached at `now`.
+    pub fn has_started(&self, now: i64) -> bool {
+        now >= self.start_ts
+    }
+
+    /// Whether the payroll's end date, if any, has passed at `now`.
+    pub fn has_ended(&self, now: i64) -> bool {
+        self.end_ts != 0 && now >= self.end_ts
+    }
+
+    /// Cycles left before the payroll completes, or `None` if it is
+    /// open-ended.
+    pub fn remaining_cycles(&self) -> Option<u64> {
+        if self.is_open_ended() {
+            return None;
+        }
+        Some(self.max_cycles.saturating_sub(self.cycles_completed))
+    }
+
+    /// How many of the next `limit` cycles after `now` the payroll still
+    /// pays before its end date, or `limit` if it has none. A prorated
+    /// payroll also pays the cycle whose period the end date falls in.
+    ///
+    /// Only the first `MAX_COUNTED_CYCLES` fires are walked; when those all
+    /// pay, all of `limit` are assumed to.
+    pub fn cycles_before_end(&self, now: i64, limit: u64) -> u64 {
+        if self.end_ts == 0 || limit == 0 {
+            return limit;
+        }
+        let schedule = match schedule::parse(&self.schedule()) {
+            Some(schedule) => schedule,
+            None => return limit,
+        };
+
+        // Fires before the start date don't pay, so the walk begins at the
+        // first that does.
+        let (from, mut previous) = if self.prorate {
+            let from = now.max(self.start_ts);
+            let previous = schedule::last_fire(&schedule, self.last_fire_ts, from);
+            (from, previous.unwrap_or(from))
+        } else {
+            let from = now.max(self.start_ts.saturating_sub(1));
+            (from, from)
+        };
+        let counted = limit.min(Self::MAX_COUNTED_CYCLES);
+        let mut cycles = 0;
+        while cycles < counted {
+            let fire = match schedule::next_fire(&schedule, previous.max(from)) {
+                Some(fire) => fire,
+                None => break,
+            };
+            let paid_from = if self.prorate { previous } else { fire };
+            if paid_from >= self.end_ts {
+                break;
+            }
+            cycles += 1;
+            previous = fire;
+        }
+        if cycles == counted {
+            limit
+        } else {
+            cycles
+        }
+    }
+
+    /// Whether the payroll pays any cycle after `now`.
+    pub fn has_cycles_left(&self, now: i64) -> bool {
+        self.remaining_cycles() != Some(0) && self.cycles_before_end(now, 1) > 0
+    }
+
+    /// Tokens one cycle costs the vault, or `None` for a quote payroll that
+    /// hasn't been priced yet.
+    pub fn cycle_cost(&self, mint_decimals: u8) -> Option<u64> {
+        if !self.pricing.is_enabled() {
+            return Some(self.amount);
+        }
+        if self.pricing.last_price == 0 {
+            return None;
+        }
+        crate::oracle::convert(
+            self.amount,
+            self.pricing.quote_decimals,
+            self.pricing.last_price,
+            self.pricing.last_expo,
+            mint_decimals,
+        )
+        .ok()
+    }
+
+    /// What the payroll owes over its remaining cycles before its end date,
+    /// from `now`, with any queued bonus and less what has been advanced. An
+    /// open-ended payroll only commits its next cycle, and an unpriced quote
+    /// payroll commits nothing until its first payment sets a price.
+    pub fn commitment(&self, mint_decimals: u8, now: i64) -> u64 {
+        let bonus = self.adjustment_total().unwrap_or(0).max(0) as u64;
+        let cycles = self.cycles_before_end(now, self.remaining_cycles().unwrap_or(1));
+        self.cycle_cost(mint_decimals)
+            .unwrap_or(0)
+            .saturating_mul(cycles)
+            .saturating_add(bonus)
+            .saturating_sub(self.advance_outstanding)
+    }
+
+    /// The most that may be advanced at once: `max_cycles` cycles' worth of
+    /// pay, no more than the payroll has left to pay. `None` for a quote
+    /// payroll that hasn't been priced yet.
+    pub fn advance_limit(&self, mint_decimals: u8, max_cycles: u64) -> Option<u64> {
+        let cycles = match self.remaining_cycles() {
+            Some(remaining) => remaining.min(max_cycles),
+            None => max_cycles,
+        };
+        Some(self.cycle_cost(mint_decimals)?.saturating_mul(cycles))
+    }
+}
+
+/// The number of cycles `balance` keeps every payroll in `cycle_costs` paid,
+/// given as `(cost per cycle, cycles remaining)` pairs, with `None` for an
+/// open-ended payroll. Payrolls drop out as they complete, so later cycles
+/// cost less. Returns `u64::MAX` when the balance outlasts every payroll.
+pub fn runway(balance: u64, cycle_costs: &[(u64, Option<u64>)]) -> u64 {
+    let mut costs: Vec<(u64, u64)> = cycle_costs
+        .iter()
+        .map(|&(cost, cycles)| (cost, cycles.unwrap_or(u64::MAX)))
+        .filter(|&(cost, cycles)| cost > 0 && cycles > 0)
+        .collect();
+    costs.sort_by_key(|&(_, cycles)| cycles);
+
+    let mut balance = balance as u128;
+    let mut per_cycle: u128 = costs.iter().map(|&(cost, _)| cost as u128).sum();
+    let mut funded = 0u64;
+    for (cost, cycles) in costs {
+        if cycles > funded {
+            let span = (cycles - funded) as u128;
+            let needed = per_cycle.saturating_mul(span);
+            if balance < needed {
+                return funded + (balance / per_cycle) as u64;
+            }
+            balance -= needed;
+            funded = cycles;
+        }
+        per_cycle -= cost as u128;
+    }
+    u64::MAX
+}
+
+/// Conversion of a payroll `amount` denominated in a quote currency, e.g. USD
+/// cents, into tokens at every `Pay`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default)]
+pub struct QuotePricing {
+    /// Price of the payroll mint in the quote currency. Unset for payrolls
+    /// whose `amount` is in tokens.
+    pub price_feed: Pubkey,
+    /// `amount` is in units of `10^-quote_decimals` of the quote currency.
+    pub quote_decimals: u8,
+    /// Oldest price accepted, in seconds.
+    pub max_price_age: u64,
+    /// Widest confidence interval accepted, in basis points of the price.
+    pub max_confidence_bps: u16,
+    /// Largest move accepted from `last_price`, in basis points.
+    pub max_slippage_bps: u16,
+    /// Price of the last payment, or at creation before the first one.
+    pub last_price: u64,
+    pub last_expo: i32,
+}
+
+impl QuotePricing {
+    pub const SIZE: usize = 32 + 1 + 8 + 2 + 2 + 8 + 4;
+
+    pub fn is_enabled(&self) -> bool {
+        self.price_feed != Pubkey::default()
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeductionKind {
+    /// A flat amount withheld every cycle.
+    #[default]
+    Fixed,
+    /// A share of the gross amount, in basis points.
+    BasisPoints,
+}
+
+/// An amount withheld from every payment and sent to `destination`
+/// instead of the receiver, e.g. income tax or a pension contribution.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default)]
+pub struct Deduction {
+    pub kind: DeductionKind,
+    pub value: u64,
+    pub destination: Pubkey,
+    pub total_withheld: u64,
+}
+
+impl Deduction {
+    pub const MAX_BASIS_POINTS: u64 = 10_000;
+    pub const SIZE: usize = 1 + 8 + 32 + 8;
+
+    /// Amount withheld from a payment of `gross`.
+    pub fn withheld_from(&self, gross: u64) -> Option<u64> {
+        match self.kind {
+            DeductionKind::Fixed => Some(self.value),
+            DeductionKind::BasisPoints => (gross as u128)
+                .checked_mul(self.value as u128)?
+                .checked_div(Self::MAX_BASIS_POINTS as u128)?
+                .try_into()
+                .ok(),
+        }
+    }
+}
+
+/// A one-off bonus, if positive, or deduction, if negative, applied to a
+/// payroll's next payment after its recurring deductions.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default)]
+pub struct Adjustment {
+    pub amount: i64,
+    /// Why the adjustment was made, as UTF-8 padded with zeroes.
+    pub memo: [u8; Adjustment::MEMO_LEN],
+}
+
+impl Adjustment {
+    pub const MEMO_LEN: usize = 32;
+    pub const SIZE: usize = 8 + Self::MEMO_LEN;
+
+    /// Encodes a memo into the fixed-size `memo` field.
+    pub fn pack_memo(memo: &str) -> Option<[u8; Self::MEMO_LEN]> {
+        let bytes = memo.as_bytes();
+        if bytes.len() > Self::MEMO_LEN {
+            return None;
+        }
+        let mut packed = [0; Self::MEMO_LEN];
+        packed[..bytes.len()].copy_from_slice(bytes);
+        Some(packed)
+    }
+
+    /// The memo as a string.
+    pub fn memo(&self) -> String {
+        let bytes: Vec<u8> = self.memo.into_iter().filter(|&x| x != 0).collect();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+}
+
+/// Per-payroll record of the most recent payments, kept as a ring buffer.
+/// A pda with seeds [b"history".as_ref(), payroll.key.as_ref()].
+///
+/// Only the header is (de)serialized as a whole; receipts are read and
+/// written in place at `receipt_offset` to keep them off the stack.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PaymentHistory {
+    pub payroll: Pubkey,
+    pub receiver: Pubkey,
+    pub receipts_recorded: u64,
+    pub bump: u8,
+}
+
+impl PaymentHistory {
+    pub const CAPACITY: usize = 12;
+    pub const HEADER_SIZE: usize = 32 + 32 + 8 + 1;
+    pub const SIZE: usize = Self::HEADER_SIZE + (Receipt::SIZE * Self::CAPACITY);
+
+    pub fn find_address(program_id: &Pubkey, payroll: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"history".as_ref(), payroll.as_ref()], program_id)
+    }
+
+    /// Byte offset of the slot the `index`th receipt is written to.
+    pub fn receipt_offset(index: u64) -> usize {
+        Self::HEADER_SIZE + (index as usize % Self::CAPACITY) * Receipt::SIZE
+    }
+
+    /// Decodes the stored receipts, oldest first.
+    pub fn unpack_receipts(data: &[u8]) -> std::io::Result<Vec<Receipt>> {
+        let header = Self::deserialize(&mut &data[..Self::HEADER_SIZE])?;
+        let stored = header.receipts_recorded.min(Self::CAPACITY as u64);
+        let first = header.receipts_recorded - stored;
+
+        (first..header.receipts_recorded)
+            .map(|index| {
+                let offset = Self::receipt_offset(index);
+                Receipt::try_from_slice(&data[offset..offset + Receipt::SIZE])
+            })
+            .collect()
+    }
+}
+
+/// A payslip for one successful `Pay`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default)]
+pub struct Receipt {
+    pub cycle: u64,
+    pub timestamp: i64,
+    pub gross_amount: u64,
+    pub deductions: [u64; Payroll::MAX_DEDUCTIONS],
+    pub receiver_wallet: Pubkey,
+    pub thread: Pubkey,
+    /// What the payroll's queued adjustments added to, or took off, the pay
+    /// left after deductions.
+    pub adjustment: i64,
+}
+
+impl Receipt {
+    pub const SIZE: usize = 8 + 8 + 8 + (8 * Payroll::MAX_DEDUCTIONS) + 32 + 32 + 8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+    /// Midnight UTC, 2023-11-15.
+    const MIDNIGHT: i64 = 1_700_006_400;
+
+    fn payroll(max_cycles: u64, cycles_completed: u64) -> Payroll {
+        let mut payroll = Payroll::try_from_slice(&[0; Payroll::SIZE]).unwrap();
+        payroll.cron_schedule = Payroll::pack_schedule("0 0 0 * * * *").unwrap();
+        payroll.max_cycles = max_cycles;
+        payroll.cycles_completed = cycles_completed;
+        payroll
+    }
+
+    #[test]
+    fn runway_of_open_ended_payrolls() {
+        assert_eq!(runway(100, &[(10, None)]), 10);
+        assert_eq!(runway(100, &[(0, None), (10, None), (15, None)]), 4);
+    }
+
+    #[test]
+    fn runway_gets_cheaper_as_payrolls_complete() {
+        // Three cycles at 20, then four more at 10.
+        assert_eq!(runway(100, &[(10, Some(10)), (10, Some(3))]), 7);
+    }
+
+    #[test]
+    fn runway_outlasting_every_payroll() {
+        assert_eq!(runway(0, &[]), u64::MAX);
+        assert_eq!(runway(30, &[(10, Some(3)), (10, Some(0))]), u64::MAX);
+    }
+
+    #[test]
+    fn remaining_cycles() {
+        assert_eq!(payroll(12, 5).remaining_cycles(), Some(7));
+        assert_eq!(payroll(12, 13).remaining_cycles(), Some(0));
+        assert_eq!(payroll(Payroll::OPEN_ENDED, 5).remaining_cycles(), None);
+    }
+
+    #[test]
+    fn payment_history_sizes_match_their_layout() {
+        assert_eq!(
+            Receipt::default().try_to_vec().unwrap().len(),
+            Receipt::SIZE
+        );
+        let header = PaymentHistory {
+            payroll: Pubkey::default(),
+            receiver: Pubkey::default(),
+            receipts_recorded: 0,
+            bump: 0,
+        };
+        assert_eq!(
+            header.try_to_vec().unwrap().len(),
+            PaymentHistory::HEADER_SIZE
+        );
+        assert_eq!(
+            PaymentHistory::receipt_offset(PaymentHistory::CAPACITY as u64 - 1) + Receipt::SIZE,
+            PaymentHistory::SIZE
+        );
+    }
+
+    #[test]
+    fn cycles_before_end() {
+        let mut payroll = payroll(12, 0);
+        assert_eq!(payroll.cycles_before_end(MIDNIGHT, 12), 12);
+
+        payroll.end_ts = MIDNIGHT + 3 * DAY;
+        assert_eq!(payroll.cycles_before_end(MIDNIGHT, 12), 2);
+        assert_eq!(payroll.cycles_before_end(MIDNIGHT, 1), 1);
+        assert!(!payroll.has_cycles_left(MIDNIGHT + 2 * DAY));
+
+        // The cycle whose period the end date falls in is prorated.
+        payroll.prorate = true;
+        assert_eq!(payroll.cycles_before_end(MIDNIGHT, 12), 3);
+
+        payroll.prorate = false;
+        payroll.start_ts = MIDNIGHT + 2 * DAY;
+        assert_eq!(payroll.cycles_before_end(MIDNIGHT, 12), 1);
+    }
 }
@@ -0,0 +1,151 @@
+//! Token account helpers that work with both the SPL Token and the
+//! Token-2022 programs.
+//!
+//! Token-2022 accounts may carry extensions after the base layout, so they
+//! are unpacked with `StateWithExtensions` rather than `Pack::unpack`, which
+//! only accepts the exact legacy length.
+//!
+//! Transfer fees on Token-2022 mints are withheld from what the destination
+//! receives: payrolls pay their net amount out of the vault, and the receiver
+//! absorbs the fee.
+
+use crate::error::ClockPayError;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use spl_token_2022::{
+    extension::{
+        default_account_state::DefaultAccountState, transfer_fee::TransferFeeConfig,
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    state::{Account, AccountState, Mint},
+};
+
+/// Mint extensions a payroll cannot work with.
+const UNSUPPORTED_MINT_EXTENSIONS: &[ExtensionType] = &[ExtensionType::NonTransferable];
+
+/// Returns an error unless `token_program` is SPL Token or Token-2022.
+pub fn check_token_program(token_program: &Pubkey) -> ProgramResult {
+    if *token_program != spl_token::ID && *token_program != spl_token_2022::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Whether `mint` is the wrapped SOL mint of either token program.
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+/// Unpacks a token account owned by `token_program`.
+pub fn unpack_token_account(
+    account: &AccountInfo,
+    token_program: &Pubkey,
+) -> Result<Account, ProgramError> {
+    if account.owner != token_program {
+        return Err(ClockPayError::InvalidAccount.into());
+    }
+    let data = account.try_borrow_data()?;
+    Ok(StateWithExtensions::<Account>::unpack(&data)?.base)
+}
+
+/// Unpacks a mint owned by `token_program`.
+pub fn unpack_mint(mint: &AccountInfo, token_program: &Pubkey) -> Result<Mint, ProgramError> {
+    if mint.owner != token_program {
+        return Err(ClockPayError::InvalidAccount.into());
+    }
+    let data = mint.try_borrow_data()?;
+    Ok(StateWithExtensions::<Mint>::unpack(&data)?.base)
+}
+
+/// Rejects mints whose extensions would stop the vault from paying out.
+pub fn check_mint_extensions(mint: &AccountInfo) -> ProgramResult {
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&data)?;
+    // This Token-2022 release predates transfer hooks, so a mint carrying one
+    // fails to parse here rather than matching an extension type. A hook
+    // program could refuse the vault's transfers, so the mint is rejected.
+    let extensions = mint_state
+        .get_extension_types()
+        .map_err(|_| ClockPayError::UnsupportedMint)?;
+    if extensions
+        .iter()
+        .any(|extension| UNSUPPORTED_MINT_EXTENSIONS.contains(extension))
+    {
+        return Err(ClockPayError::UnsupportedMint.into());
+    }
+    // Receivers' token accounts would be created frozen and couldn't be paid.
+    if let Ok(default_state) = mint_state.get_extension::<DefaultAccountState>() {
+        if default_state.state == AccountState::Frozen as u8 {
+            return Err(ClockPayError::UnsupportedMint.into());
+        }
+    }
+    Ok(())
+}
+
+/// Fee the token program withholds from a transfer of `amount`, which the
+/// destination does not receive. Zero unless the mint has a transfer fee.
+/// `Pay` doesn't gross its transfers up by it, so receivers bear the fee.
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&data)?;
+    let fee_config = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => fee_config,
+        Err(_) => return Ok(0),
+    };
+    fee_config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ProgramError::InvalidArgument)
+}
+
+/// Brings a wrapped SOL account's token balance up to date with lamports
+/// transferred into it directly.
+pub fn sync_native<'a>(
+    token_program: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+) -> ProgramResult {
+    let sync_ix = spl_token_2022::instruction::sync_native(token_program.key, account.key)?;
+    invoke(&sync_ix, &[account.clone(), token_program.clone()])
+}
+
+/// Transfers `amount` with `transfer_checked`, which both token programs
+/// support and Token-2022 requires for mints with a transfer fee.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+    decimals: u8,
+) -> ProgramResult {
+    let transfer_ix = spl_token_2022::instruction::transfer_checked(
+        token_program.key,
+        source.key,
+        mint.key,
+        destination.key,
+        authority.key,
+        &[],
+        amount,
+        decimals,
+    )?;
+
+    invoke_signed(
+        &transfer_ix,
+        &[
+            source.clone(),
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
@@ -0,0 +1,305 @@
+//! Accounting and payroll instructions run against the program in a local
+//! bank, up to the point a payroll's thread would be created.
+
+use borsh::BorshDeserialize;
+use program::{
+    error::ClockPayError,
+    instruction::{self, NewPayrollArgs, UpdatePayrollArgs, WithdrawArgs},
+    oracle::PythOracle,
+    processor::Processor,
+    state::{Accounting, Payroll},
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+
+const DEPOSIT: u64 = 1_000;
+const AMOUNT: u64 = 100;
+const CYCLES: u64 = 5;
+
+struct Env {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    program_id: Pubkey,
+    mint: Pubkey,
+    /// The payer's token account, which deposits come from and withdrawals
+    /// go to.
+    treasury: Pubkey,
+}
+
+impl Env {
+    /// An accounting funded with `DEPOSIT` tokens.
+    async fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "program",
+            program_id,
+            processor!(Processor::process::<PythOracle>),
+        );
+        let (banks_client, payer, recent_blockhash) = program_test.start().await;
+
+        let mut env = Env {
+            banks_client,
+            payer,
+            recent_blockhash,
+            program_id,
+            mint: Pubkey::default(),
+            treasury: Pubkey::default(),
+        };
+        env.mint = env.create_mint().await;
+        env.treasury = get_associated_token_address(&env.payer.pubkey(), &env.mint);
+        let instructions = [
+            create_associated_token_account(
+                &env.payer.pubkey(),
+                &env.payer.pubkey(),
+                &env.mint,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &env.mint,
+                &env.treasury,
+                &env.payer.pubkey(),
+                &[],
+                DEPOSIT,
+            )
+            .unwrap(),
+            instruction::init_accounting(
+                &env.program_id,
+                &env.payer.pubkey(),
+                &env.mint,
+                &spl_token::id(),
+            ),
+            instruction::deposit(
+                &env.program_id,
+                &env.payer.pubkey(),
+                &env.mint,
+                &spl_token::id(),
+                None,
+                &env.treasury,
+                DEPOSIT,
+            ),
+        ];
+        env.send(&instructions, &[]).await.unwrap();
+        env
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.recent_blockhash,
+        );
+        self.banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    async fn create_mint(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self.banks_client.get_rent().await.unwrap();
+        let instructions = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &self.payer.pubkey(),
+                None,
+                0,
+            )
+            .unwrap(),
+        ];
+        self.send(&instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    /// Creates a payroll of `AMOUNT` tokens a day for `cycles` cycles.
+    async fn new_payroll(
+        &mut self,
+        receiver: &Pubkey,
+        cycles: u64,
+    ) -> Result<(), TransactionError> {
+        let args = NewPayrollArgs {
+            amount: AMOUNT,
+            cycles,
+            schedule: Payroll::pack_schedule("0 0 0 * * * *").unwrap(),
+            withholding: vec![],
+            decimals: None,
+            mint: None,
+            quote: None,
+            allow_overcommit: false,
+            start_ts: None,
+            end_ts: None,
+            prorate: false,
+        };
+        let new_payroll = instruction::new_payroll(
+            &self.program_id,
+            &self.payer.pubkey(),
+            receiver,
+            &args,
+            None,
+            None,
+            &[],
+        );
+        self.send(&[new_payroll], &[]).await
+    }
+
+    async fn update_payroll(
+        &mut self,
+        receiver: &Pubkey,
+        cycles: u64,
+    ) -> Result<(), TransactionError> {
+        let args = UpdatePayrollArgs {
+            amount: None,
+            cycles: Some(cycles),
+            allow_overcommit: false,
+        };
+        let update = instruction::update_payroll(
+            &self.program_id,
+            &self.payer.pubkey(),
+            receiver,
+            &args,
+            None,
+        );
+        self.send(&[update], &[]).await
+    }
+
+    async fn withdraw(&mut self, amount: u64) -> Result<(), TransactionError> {
+        let withdraw = instruction::withdraw(
+            &self.program_id,
+            &self.payer.pubkey(),
+            &self.mint,
+            &spl_token::id(),
+            None,
+            &self.treasury,
+            &WithdrawArgs {
+                amount,
+                allow_overcommit: false,
+            },
+        );
+        self.send(&[withdraw], &[]).await
+    }
+
+    async fn accounting(&mut self) -> Accounting {
+        let (accounting, _) = Accounting::find_address(&self.program_id, &self.payer.pubkey());
+        let account = self
+            .banks_client
+            .get_account(accounting)
+            .await
+            .unwrap()
+            .unwrap();
+        Accounting::try_from_slice(&account.data).unwrap()
+    }
+
+    async fn payroll(&mut self, receiver: &Pubkey) -> Option<Payroll> {
+        let (accounting, _) = Accounting::find_address(&self.program_id, &self.payer.pubkey());
+        let (payroll, _) = Payroll::find_address(&self.program_id, &accounting, receiver);
+        let account = self.banks_client.get_account(payroll).await.unwrap()?;
+        Some(Payroll::try_from_slice(&account.data).unwrap())
+    }
+}
+
+fn custom_error(error: ClockPayError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn new_payroll_commits_its_cycles() {
+    let mut env = Env::new().await;
+    let receiver = Pubkey::new_unique();
+    env.new_payroll(&receiver, CYCLES).await.unwrap();
+
+    let payroll = env.payroll(&receiver).await.unwrap();
+    assert_eq!(payroll.committed, AMOUNT * CYCLES);
+    assert!(!payroll.active);
+    let accounting = env.accounting().await;
+    assert_eq!(accounting.balance, DEPOSIT);
+    assert_eq!(accounting.committed_amount, AMOUNT * CYCLES);
+}
+
+#[tokio::test]
+async fn new_payroll_refuses_zero_cycles() {
+    let mut env = Env::new().await;
+    let receiver = Pubkey::new_unique();
+    let error = env.new_payroll(&receiver, 0).await.unwrap_err();
+    assert_eq!(error, custom_error(ClockPayError::InvalidCycles));
+    assert!(env.payroll(&receiver).await.is_none());
+}
+
+#[tokio::test]
+async fn update_payroll_refuses_zero_cycles_and_overcommitting() {
+    let mut env = Env::new().await;
+    let receiver = Pubkey::new_unique();
+    env.new_payroll(&receiver, CYCLES).await.unwrap();
+
+    let error = env.update_payroll(&receiver, 0).await.unwrap_err();
+    assert_eq!(error, custom_error(ClockPayError::InvalidCycles));
+    let error = env
+        .update_payroll(&receiver, DEPOSIT / AMOUNT + 1)
+        .await
+        .unwrap_err();
+    assert_eq!(error, custom_error(ClockPayError::Insolvent));
+
+    env.update_payroll(&receiver, DEPOSIT / AMOUNT)
+        .await
+        .unwrap();
+    let payroll = env.payroll(&receiver).await.unwrap();
+    assert_eq!(payroll.committed, DEPOSIT);
+}
+
+#[tokio::test]
+async fn withdraw_refuses_to_leave_the_vault_insolvent() {
+    let mut env = Env::new().await;
+    env.new_payroll(&Pubkey::new_unique(), CYCLES)
+        .await
+        .unwrap();
+
+    let error = env
+        .withdraw(DEPOSIT - AMOUNT * CYCLES + 1)
+        .await
+        .unwrap_err();
+    assert_eq!(error, custom_error(ClockPayError::Insolvent));
+
+    env.withdraw(DEPOSIT - AMOUNT * CYCLES).await.unwrap();
+    assert_eq!(env.accounting().await.balance, AMOUNT * CYCLES);
+}
+
+#[tokio::test]
+async fn cancel_releases_an_unstarted_payroll() {
+    let mut env = Env::new().await;
+    let receiver = Pubkey::new_unique();
+    env.new_payroll(&receiver, CYCLES).await.unwrap();
+
+    let cancel =
+        instruction::cancel_payroll(&env.program_id, &env.payer.pubkey(), &receiver, None, false);
+    env.send(&[cancel], &[]).await.unwrap();
+
+    assert!(env.payroll(&receiver).await.is_none());
+    assert_eq!(env.accounting().await.committed_amount, 0);
+    env.withdraw(DEPOSIT).await.unwrap();
+}
@@ -26,21 +26,72 @@ export function serializeDepositArgs(amount: BN): Uint8Array {
 }
 
 
+export enum DeductionKind {
+  Fixed = 0,
+  BasisPoints = 1,
+}
+
+export class DeductionArgs {
+  kind = DeductionKind.Fixed;
+  value = new BN(0);
+  constructor(fields: {kind: DeductionKind, value: BN} | undefined = undefined) {
+    if (fields) {
+      this.kind = fields.kind;
+      this.value = fields.value;
+    }
+  }
+}
+
+export class QuoteArgs {
+  quoteDecimals = 0;
+  maxPriceAge = new BN(0);
+  maxConfidenceBps = 0;
+  maxSlippageBps = 0;
+  constructor(fields: {quoteDecimals: number, maxPriceAge: BN, maxConfidenceBps: number,
+    maxSlippageBps: number} | undefined = undefined) {
+    if (fields) {
+      this.quoteDecimals = fields.quoteDecimals;
+      this.maxPriceAge = fields.maxPriceAge;
+      this.maxConfidenceBps = fields.maxConfidenceBps;
+      this.maxSlippageBps = fields.maxSlippageBps;
+    }
+  }
+}
+
 class NewPayrollArgs {
   amount = new BN(0);
   cycles = new BN(0);
   schedule = Buffer.from("", "utf8");
-  constructor (fields: {amount: BN, cycles: BN, schedule: Buffer} 
+  withholding: DeductionArgs[] = [];
+  decimals: number | null = null;
+  mint: Uint8Array | null = null;
+  quote: QuoteArgs | null = null;
+  allowOvercommit = false;
+  startTs: Uint8Array | null = null;
+  endTs: Uint8Array | null = null;
+  prorate = false;
+  constructor (fields: {amount: BN, cycles: BN, schedule: Buffer, withholding: DeductionArgs[],
+    decimals: number | null, mint: Uint8Array | null, quote: QuoteArgs | null,
+    allowOvercommit: boolean, startTs: Uint8Array | null, endTs: Uint8Array | null,
+    prorate: boolean}
     | undefined = undefined) {
       if (fields) {
         this.amount = fields.amount;
         this.cycles = fields.cycles;
         this.schedule = fields.schedule;
+        this.withholding = fields.withholding;
+        this.decimals = fields.decimals;
+        this.mint = fields.mint;
+        this.quote = fields.quote;
+        this.allowOvercommit = fields.allowOvercommit;
+        this.startTs = fields.startTs;
+        this.endTs = fields.endTs;
+        this.prorate = fields.prorate;
       }
     }
 }
 
-const NewPayrollSchema = new Map([
+const NewPayrollSchema = new Map<any, any>([
   [
     NewPayrollArgs, 
     {
@@ -49,13 +100,47 @@ const NewPayrollSchema = new Map([
         ['amount', 'u64'],
         ['cycles', 'u64'],
         ['schedule', [30]],
+        ['withholding', [DeductionArgs]],
+        ['decimals', { kind: 'option', type: 'u8' }],
+        ['mint', { kind: 'option', type: [32] }],
+        ['quote', { kind: 'option', type: QuoteArgs }],
+        ['allowOvercommit', 'u8'],
+        // i64s, which borsh-js can't encode
+        ['startTs', { kind: 'option', type: [8] }],
+        ['endTs', { kind: 'option', type: [8] }],
+        ['prorate', 'u8'],
+      ]
+    }
+  ],
+  [
+    DeductionArgs,
+    {
+      kind: 'struct',
+      fields: [
+        ['kind', 'u8'],
+        ['value', 'u64'],
+      ]
+    }
+  ],
+  [
+    QuoteArgs,
+    {
+      kind: 'struct',
+      fields: [
+        ['quoteDecimals', 'u8'],
+        ['maxPriceAge', 'u64'],
+        ['maxConfidenceBps', 'u16'],
+        ['maxSlippageBps', 'u16'],
       ]
     }
   ]
 ]);
 
 export function serializeNewPayrollArgs(
-  amount: BN, cycles: BN, schedule: string): Uint8Array {
+  amount: BN, cycles: BN, schedule: string, withholding: DeductionArgs[] = [],
+  decimals: number | null = null, mint: PublicKey | null = null,
+  quote: QuoteArgs | null = null, allowOvercommit = false,
+  startTs: BN | null = null, endTs: BN | null = null, prorate = false): Uint8Array {
   let bytes = Buffer.alloc(30);
   bytes.write(schedule, "utf8");
 
@@ -65,12 +150,25 @@ export function serializeNewPayrollArgs(
       amount: amount,
       cycles: cycles,
       schedule: bytes,
+      withholding: withholding,
+      decimals: decimals,
+      mint: mint ? mint.toBytes() : null,
+      quote: quote,
+      allowOvercommit: allowOvercommit,
+      startTs: startTs ? startTs.toTwos(64).toArrayLike(Buffer, 'le', 8) : null,
+      endTs: endTs ? endTs.toTwos(64).toArrayLike(Buffer, 'le', 8) : null,
+      prorate: prorate,
     }),
   );
   return args;
 }
 
 
+export enum FundingMode {
+  Custodial = 0,
+  Delegated = 1,
+}
+
 export class AccountingState {
   authority = PublicKey.default;
   mint = PublicKey.default;
@@ -79,9 +177,20 @@ export class AccountingState {
   balance = new BN(0);
   active = false;
   bump = 0;
+  fundingMode = FundingMode.Custodial;
+  tokenProgram = PublicKey.default;
+  decimals = 0;
+  feeReserve = new BN(0);
+  threadMinBalance = new BN(0);
+  committedAmount = new BN(0);
+  transferApproval = false;
+  maxAdvanceCycles = new BN(0);
   constructor(fields: {
     authority: Uint8Array, mint: Uint8Array, active_payrolls: BN, 
-    vault: Uint8Array, balance: BN, active: boolean, bump: number
+    vault: Uint8Array, balance: BN, active: boolean, bump: number,
+    funding_mode: number, token_program: Uint8Array, decimals: number,
+    fee_reserve: BN, thread_min_balance: BN, committed_amount: BN,
+    transfer_approval: boolean, max_advance_cycles: BN,
   } | undefined = undefined) {
     if (fields) {
       this.authority = new PublicKey(fields.authority);
@@ -91,6 +200,14 @@ export class AccountingState {
       this.balance = fields.balance;
       this.active = fields.active;
       this.bump = fields.bump;
+      this.fundingMode = fields.funding_mode;
+      this.tokenProgram = new PublicKey(fields.token_program);
+      this.decimals = fields.decimals;
+      this.feeReserve = fields.fee_reserve;
+      this.threadMinBalance = fields.thread_min_balance;
+      this.committedAmount = fields.committed_amount;
+      this.transferApproval = fields.transfer_approval;
+      this.maxAdvanceCycles = fields.max_advance_cycles;
     }
   }
 }
@@ -108,6 +225,14 @@ export const AccountingSchema = new Map([
         ['balance', 'u64'],
         ['active', 'u8'],
         ['bump', 'u8'],
+        ['funding_mode', 'u8'],
+        ['token_program', [32]],
+        ['decimals', 'u8'],
+        ['fee_reserve', 'u64'],
+        ['thread_min_balance', 'u64'],
+        ['committed_amount', 'u64'],
+        ['transfer_approval', 'u8'],
+        ['max_advance_cycles', 'u64'],
       ]
     }
   ]
@@ -123,21 +248,94 @@ export function deserializeAccountingState(data: Buffer): AccountingState {
 }
 
 
+export class Deduction {
+  kind = DeductionKind.Fixed;
+  value = new BN(0);
+  destination = PublicKey.default;
+  totalWithheld = new BN(0);
+  constructor(fields: {
+    kind: number, value: BN, destination: Uint8Array, total_withheld: BN
+  } | undefined = undefined) {
+    if (fields) {
+      this.kind = fields.kind;
+      this.value = fields.value;
+      this.destination = new PublicKey(fields.destination);
+      this.totalWithheld = fields.total_withheld;
+    }
+  }
+}
+
+export class QuotePricing {
+  priceFeed = PublicKey.default;
+  quoteDecimals = 0;
+  maxPriceAge = new BN(0);
+  maxConfidenceBps = 0;
+  maxSlippageBps = 0;
+  lastPrice = new BN(0);
+  lastExpo = 0;
+  constructor(fields: {
+    price_feed: Uint8Array, quote_decimals: number, max_price_age: BN, max_confidence_bps: number,
+    max_slippage_bps: number, last_price: BN, last_expo: Uint8Array
+  } | undefined = undefined) {
+    if (fields) {
+      this.priceFeed = new PublicKey(fields.price_feed);
+      this.quoteDecimals = fields.quote_decimals;
+      this.maxPriceAge = fields.max_price_age;
+      this.maxConfidenceBps = fields.max_confidence_bps;
+      this.maxSlippageBps = fields.max_slippage_bps;
+      this.lastPrice = fields.last_price;
+      // borsh-js has no signed integers
+      this.lastExpo = Buffer.from(fields.last_expo).readInt32LE(0);
+    }
+  }
+}
+
+export class Adjustment {
+  amount = new BN(0);
+  memo = "";
+  constructor(fields: {amount: Uint8Array, memo: Uint8Array} | undefined = undefined) {
+    if (fields) {
+      // borsh-js has no signed integers
+      this.amount = new BN(fields.amount, 'le').fromTwos(64);
+      this.memo = new TextDecoder().decode(fields.memo.filter(x => x != 0));
+    }
+  }
+}
+
+export const MAX_DEDUCTIONS = 4;
+export const MAX_ADJUSTMENTS = 4;
+// `maxCycles` of a payroll that pays until it is cancelled.
+export const OPEN_ENDED = new BN("18446744073709551615");
+
 export class PayRoll {
   accounting = PublicKey.default;
   active = false;
   amount = new BN(0);
   totalAmountDisbursed = new BN(0);
+  deductions: Deduction[] = [];
   cronSchedule = "";
   receiver = PublicKey.default;
   maxCycles = new BN(0);
   cyclesCompleted = new BN(0);
   thread = PublicKey.default;
   bump = 0;
+  mint = PublicKey.default;
+  pricing = new QuotePricing();
+  committed = new BN(0);
+  startTs = new BN(0);
+  endTs = new BN(0);
+  prorate = false;
+  payoutAccount = PublicKey.default;
+  advanceOutstanding = new BN(0);
+  adjustments: Adjustment[] = [];
+  lastFireTs = new BN(0);
   constructor(fields: {
     accounting: Uint8Array, active: boolean, amount: BN, total_amount_disbursed: BN,
-    cron_schedule: Uint8Array, receiver: Uint8Array, max_cycles: BN, cycles_completed: BN, 
-    thread: Uint8Array, bump: number} | undefined = undefined) 
+    deduction_count: number, deductions: Deduction[], cron_schedule: Uint8Array, receiver: Uint8Array, max_cycles: BN, cycles_completed: BN, 
+    thread: Uint8Array, bump: number, mint: Uint8Array, pricing: QuotePricing,
+    committed: BN, start_ts: Uint8Array, end_ts: Uint8Array,
+    prorate: boolean, payout_account: Uint8Array, advance_outstanding: BN,
+    adjustment_count: number, adjustments: Adjustment[], last_fire_ts: Uint8Array} | undefined = undefined) 
   {
     if(fields) {
       let schedule = fields.cron_schedule.filter(x => x != 0);
@@ -146,17 +344,69 @@ export class PayRoll {
       this.active = fields.active;
       this.amount = fields.amount;
       this.totalAmountDisbursed = fields.total_amount_disbursed;
+      this.deductions = fields.deductions.slice(0, fields.deduction_count);
       this.cronSchedule = new TextDecoder().decode(schedule),
       this.receiver = new PublicKey(fields.receiver);
       this.maxCycles = fields.max_cycles;
       this.cyclesCompleted = fields.cycles_completed;
       this.thread = new PublicKey(fields.thread);
       this.bump = fields.bump;
+      this.mint = new PublicKey(fields.mint);
+      this.pricing = fields.pricing;
+      this.committed = fields.committed;
+      this.startTs = new BN(fields.start_ts, 'le').fromTwos(64);
+      this.endTs = new BN(fields.end_ts, 'le').fromTwos(64);
+      this.prorate = fields.prorate;
+      this.payoutAccount = new PublicKey(fields.payout_account);
+      this.advanceOutstanding = fields.advance_outstanding;
+      this.adjustments = fields.adjustments.slice(0, fields.adjustment_count);
+      this.lastFireTs = new BN(fields.last_fire_ts, 'le').fromTwos(64);
     }
   }
+
+  isOpenEnded(): boolean {
+    return this.maxCycles.eq(OPEN_ENDED);
+  }
 }
 
-export const PayrollSchema = new Map([
+export const PayrollSchema = new Map<any, any>([
+  [
+    Deduction,
+    {
+      kind: 'struct',
+      fields: [
+        ['kind', 'u8'],
+        ['value', 'u64'],
+        ['destination', [32]],
+        ['total_withheld', 'u64'],
+      ]
+    }
+  ],
+  [
+    Adjustment,
+    {
+      kind: 'struct',
+      fields: [
+        ['amount', [8]],
+        ['memo', [32]],
+      ]
+    }
+  ],
+  [
+    QuotePricing,
+    {
+      kind: 'struct',
+      fields: [
+        ['price_feed', [32]],
+        ['quote_decimals', 'u8'],
+        ['max_price_age', 'u64'],
+        ['max_confidence_bps', 'u16'],
+        ['max_slippage_bps', 'u16'],
+        ['last_price', 'u64'],
+        ['last_expo', [4]],
+      ]
+    }
+  ],
   [
     PayRoll,
     {
@@ -166,12 +416,25 @@ export const PayrollSchema = new Map([
         ['active', 'u8'],
         ['amount', 'u64'],
         ['total_amount_disbursed', 'u64'],
+        ['deduction_count', 'u8'],
+        ['deductions', [Deduction, MAX_DEDUCTIONS]],
         ['cron_schedule', [30]],
         ['receiver', [32]],
         ['max_cycles', 'u64'],
         ['cycles_completed', 'u64'],
         ['thread', [32]],
         ['bump', 'u8'],
+        ['mint', [32]],
+        ['pricing', QuotePricing],
+        ['committed', 'u64'],
+        ['start_ts', [8]],
+        ['end_ts', [8]],
+        ['prorate', 'u8'],
+        ['payout_account', [32]],
+        ['advance_outstanding', 'u64'],
+        ['adjustment_count', 'u8'],
+        ['adjustments', [Adjustment, MAX_ADJUSTMENTS]],
+        ['last_fire_ts', [8]],
       ]
     }
   ]
@@ -154,6 +154,11 @@ describe("crowdfunding", () => {
           isSigner: false,
           isWritable: false,
         },
+        {
+          pubkey: tokenX,
+          isSigner: false,
+          isWritable: false,
+        },
       ],
       data: instructionData
     });
@@ -281,6 +286,10 @@ describe("crowdfunding", () => {
       console.log("   Payroll: ", payroll.toString());
       await airdrop(connection, thread, 10);
 
+      let [history] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history", "utf8"), payroll.toBuffer()], programId
+      );
+
       let ix = new TransactionInstruction({
         programId: programId,
         keys: [
@@ -329,6 +338,11 @@ describe("crowdfunding", () => {
             isSigner: false,
             isWritable: false,
           },
+          {
+            pubkey: history,
+            isSigner: false,
+            isWritable: true,
+          },
         ],
         data: Buffer.from([3])
       });